@@ -0,0 +1,151 @@
+// Storage abstraction the account services are written against
+
+use crate::gjp2::Gjp2;
+#[cfg(test)]
+use crate::activation::ActivationPolicy;
+#[cfg(test)]
+use crate::gjp2::Gjp2Generator;
+use crate::user::{Email, Name, User};
+
+pub struct Account {
+    pub user: User,
+    /// ID of the player profile tied to the account, separate from the
+    /// account ID since unregistered players have one too
+    pub user_id: u64,
+    pub gjp2: Gjp2,
+    pub steam_id: Option<u64>,
+}
+
+/// Implementations must compare names by their [canonical](Name::canonical)
+/// form and emails case-insensitively, the client treats "Babygronk" and
+/// "babygronk" as the same account.
+pub trait AccountStore {
+    fn name_taken(&self, name: &Name) -> bool;
+    /// Whether a name with the same [skeleton](Name::skeleton) exists.
+    fn confusable_name_taken(&self, name: &Name) -> bool;
+    fn email_taken(&self, email: &Email) -> bool;
+    fn next_account_id(&mut self) -> u64;
+    fn next_user_id(&mut self) -> u64;
+    fn insert(&mut self, account: Account);
+    fn find_by_name(&self, name: &Name) -> Option<&Account>;
+    fn find_by_id(&self, account_id: u64) -> Option<&Account>;
+    fn find_by_id_mut(&mut self, account_id: u64) -> Option<&mut Account>;
+    fn update_gjp2(&mut self, account_id: u64, gjp2: Gjp2);
+}
+
+/// An account around `user` with placeholder credentials, for tests that
+/// don't log in.
+#[cfg(test)]
+pub(crate) fn test_account(user: User) -> Account {
+    Account {
+        user_id: user.id,
+        user,
+        gjp2: Gjp2::new(""),
+        steam_id: None,
+    }
+}
+
+/// A generator with the given bcrypt cost, tests keep it low to stay fast.
+#[cfg(test)]
+pub(crate) fn test_generator(cost: u32) -> Gjp2Generator {
+    use sha1::{Digest, Sha1};
+
+    Gjp2Generator::with_cost(Sha1::new(), cost).unwrap()
+}
+
+/// Registers an account with the password `_deez-nuts` and returns its ID.
+#[cfg(test)]
+pub(crate) fn test_register(
+    store: &mut impl AccountStore,
+    user_name: &str,
+    email: &str,
+    activation: ActivationPolicy
+) -> u64 {
+    use crate::register::{RegisterRequest, Registrar};
+
+    Registrar::new(test_generator(4), activation)
+        .register(store, &RegisterRequest {
+            user_name,
+            password: "_deez-nuts",
+            password_confirmation: None,
+            email,
+        })
+        .unwrap()
+}
+
+#[derive(Default)]
+pub struct MemoryAccountStore {
+    accounts: Vec<Account>,
+    user_count: u64,
+}
+
+impl MemoryAccountStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn accounts(&self) -> &[Account] {
+        &self.accounts
+    }
+
+    pub fn accounts_mut(&mut self) -> &mut [Account] {
+        &mut self.accounts
+    }
+}
+
+impl AccountStore for MemoryAccountStore {
+    fn name_taken(&self, name: &Name) -> bool {
+        self.find_by_name(name).is_some()
+    }
+
+    fn confusable_name_taken(&self, name: &Name) -> bool {
+        let skeleton = name.skeleton();
+        self.accounts
+            .iter()
+            .any(|account| account.user.name.skeleton() == skeleton)
+    }
+
+    fn email_taken(&self, email: &Email) -> bool {
+        self.accounts.iter().any(|account| {
+            account.user.email.as_str().eq_ignore_ascii_case(email.as_str())
+        })
+    }
+
+    fn next_account_id(&mut self) -> u64 {
+        self.accounts.len() as u64 + 1
+    }
+
+    fn next_user_id(&mut self) -> u64 {
+        self.user_count += 1;
+        self.user_count
+    }
+
+    fn insert(&mut self, account: Account) {
+        self.accounts.push(account);
+    }
+
+    fn find_by_name(&self, name: &Name) -> Option<&Account> {
+        let canonical = name.canonical();
+        self.accounts
+            .iter()
+            .find(|account| account.user.name.canonical() == canonical)
+    }
+
+    fn find_by_id(&self, account_id: u64) -> Option<&Account> {
+        self.accounts
+            .iter()
+            .find(|account| account.user.id == account_id)
+    }
+
+    fn find_by_id_mut(&mut self, account_id: u64) -> Option<&mut Account> {
+        self.accounts
+            .iter_mut()
+            .find(|account| account.user.id == account_id)
+    }
+
+    fn update_gjp2(&mut self, account_id: u64, gjp2: Gjp2) {
+        if let Some(account) = self.find_by_id_mut(account_id) {
+            account.gjp2 = gjp2;
+        }
+    }
+}
@@ -0,0 +1,237 @@
+// Email verification for new accounts, the client refuses to log into
+// accounts that aren't activated
+
+use crate::account::AccountStore;
+use crate::token;
+use crate::user::{Activation, Email};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum ActivationPolicy {
+    EmailVerification,
+    /// Activates accounts as soon as they register, for small private
+    /// servers that don't want to set up mail
+    AutoActivate,
+}
+
+#[derive(PartialEq, Debug)]
+pub struct Mail {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+pub trait Outbox {
+    fn send(&mut self, mail: Mail) -> io::Result<()>;
+}
+
+/// Appends mails to a file instead of sending them, for testing and for
+/// admins activating accounts by hand.
+pub struct FileOutbox {
+    path: PathBuf,
+}
+
+impl FileOutbox {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+        }
+    }
+}
+
+impl Outbox for FileOutbox {
+    fn send(&mut self, mail: Mail) -> io::Result<()> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+
+        writeln!(file, "To: {}", mail.to)?;
+        writeln!(file, "Subject: {}", mail.subject)?;
+        writeln!(file)?;
+        writeln!(file, "{}", mail.body)?;
+        writeln!(file)
+    }
+}
+
+#[derive(Debug)]
+pub enum ActivationError {
+    InvalidToken,
+    Random(getrandom::Error),
+    Outbox(io::Error),
+}
+
+impl fmt::Display for ActivationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::InvalidToken => {
+                write!(f, "activation token is invalid or expired")
+            }
+            Self::Random(_) => write!(f, "failed to generate activation token"),
+            Self::Outbox(_) => write!(f, "failed to send activation mail"),
+        }
+    }
+}
+
+impl Error for ActivationError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::InvalidToken => None,
+            Self::Random(error) => Some(error),
+            Self::Outbox(error) => Some(error),
+        }
+    }
+}
+
+struct PendingActivation {
+    account_id: u64,
+    expires_at: Instant,
+}
+
+pub struct Activator<O: Outbox> {
+    outbox: O,
+    /// Prepended to the token to form the link in the mail
+    link_base: String,
+    ttl: Duration,
+    pending: HashMap<String, PendingActivation>,
+}
+
+impl<O: Outbox> Activator<O> {
+    pub fn new(outbox: O, link_base: &str, ttl: Duration) -> Self {
+        Self {
+            outbox,
+            link_base: link_base.to_owned(),
+            ttl,
+            pending: HashMap::new(),
+        }
+    }
+
+    /// Sends a verification mail with a fresh single-use token, replacing
+    /// any previously sent to the account.
+    pub fn send_verification(
+        &mut self,
+        account_id: u64,
+        email: &Email,
+        now: Instant
+    ) -> Result<(), ActivationError> {
+        let token = token::random_token().map_err(ActivationError::Random)?;
+        let mail = Mail {
+            to: email.as_str().to_owned(),
+            subject: "Activate your account".to_owned(),
+            body: format!("{}{}", self.link_base, token),
+        };
+
+        self.outbox.send(mail).map_err(ActivationError::Outbox)?;
+        self.pending.retain(|_, pending| pending.account_id != account_id);
+        self.pending.insert(
+            token,
+            PendingActivation {
+                account_id,
+                expires_at: now + self.ttl,
+            }
+        );
+
+        Ok(())
+    }
+
+    /// Activates the token's account and returns its ID.
+    pub fn activate(
+        &mut self,
+        store: &mut impl AccountStore,
+        token: &str,
+        now: Instant
+    ) -> Result<u64, ActivationError> {
+        let account_id = match self.pending.remove(token) {
+            Some(pending) if now < pending.expires_at => pending.account_id,
+            _ => return Err(ActivationError::InvalidToken),
+        };
+
+        let account = store
+            .find_by_id_mut(account_id)
+            .ok_or(ActivationError::InvalidToken)?;
+        account.user.activation = Activation::Active;
+
+        Ok(account_id)
+    }
+
+    /// Forgets tokens that expired without being used, the accounts stay
+    /// pending until a new mail is sent.
+    pub fn purge_expired(&mut self, now: Instant) {
+        self.pending.retain(|_, pending| now < pending.expires_at);
+    }
+
+    pub fn outbox(&self) -> &O {
+        &self.outbox
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account::{test_register, MemoryAccountStore};
+    use std::fs;
+    use std::process;
+
+    const LINK: &str = "https://gdps.example/activate/";
+
+    fn sent_tokens(path: &PathBuf) -> Vec<String> {
+        fs::read_to_string(path)
+            .unwrap()
+            .lines()
+            .filter_map(|line| line.strip_prefix(LINK))
+            .map(str::to_owned)
+            .collect()
+    }
+
+    #[test]
+    fn test_activation() -> Result<(), ActivationError> {
+        let file = format!("geometry-deez-activation-{}.txt", process::id());
+        let path = std::env::temp_dir().join(file);
+        let _ = fs::remove_file(&path);
+
+        let policy = ActivationPolicy::EmailVerification;
+        let mut store = MemoryAccountStore::new();
+        let id = test_register(&mut store, "babygronk", "a@b.cd", policy);
+
+        let ttl = Duration::from_secs(60);
+        let now = Instant::now();
+        let email = Email::parse("a@b.cd").unwrap();
+        let mut activator = Activator::new(FileOutbox::new(&path), LINK, ttl);
+
+        activator.send_verification(id, &email, now)?;
+        activator.send_verification(id, &email, now)?;
+        let tokens = sent_tokens(&path);
+
+        assert_eq!(tokens.len(), 2);
+        assert!(fs::read_to_string(&path).unwrap().starts_with("To: a@b.cd\n"));
+        assert!(matches!(
+            activator.activate(&mut store, &tokens[0], now),
+            Err(ActivationError::InvalidToken)
+        ));
+        assert!(matches!(
+            activator.activate(&mut store, &tokens[1], now + ttl),
+            Err(ActivationError::InvalidToken)
+        ));
+
+        activator.send_verification(id, &email, now)?;
+        let token = sent_tokens(&path).pop().unwrap();
+        activator.purge_expired(now);
+
+        assert_eq!(store.accounts()[0].user.activation, Activation::Pending);
+        assert_eq!(activator.activate(&mut store, &token, now)?, id);
+        assert_eq!(store.accounts()[0].user.activation, Activation::Active);
+        assert!(matches!(
+            activator.activate(&mut store, &token, now),
+            Err(ActivationError::InvalidToken)
+        ));
+
+        let _ = fs::remove_file(&path);
+        Ok(())
+    }
+}
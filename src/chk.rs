@@ -0,0 +1,184 @@
+// Integrity checksums sent by the client in the `chk` field of uploads, and
+// the prefixed encoding used for reward and challenge payloads
+
+use crate::xor::{self, XorError, XorKey};
+use sha1::{Digest, Sha1};
+use subtle::ConstantTimeEq;
+use std::error::Error;
+use std::fmt;
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum ChkKind {
+    Level,
+    Comment,
+    LikeOrRate,
+    LevelLeaderboard,
+    Stats,
+}
+
+impl ChkKind {
+    fn salt(&self) -> &'static str {
+        match self {
+            Self::Level => "xI25fpAapCQg",
+            Self::Comment => "xPT6iUrtws0J",
+            Self::LikeOrRate => "ysg6pUrtjn0J",
+            Self::LevelLeaderboard => "yPg6pUrtWn0J",
+            Self::Stats => "xI35fsAapCRg",
+        }
+    }
+
+    fn key(&self) -> XorKey {
+        match self {
+            Self::Level => XorKey::Level,
+            Self::Comment => XorKey::Comment,
+            Self::LikeOrRate => XorKey::LikeOrRate,
+            Self::LevelLeaderboard => XorKey::LevelLeaderboard,
+            Self::Stats => XorKey::Stats,
+        }
+    }
+}
+
+pub(crate) fn sha1_hex(input: &str) -> String {
+    Sha1::digest(input)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+fn digest(kind: ChkKind, fields: &[&str]) -> String {
+    sha1_hex(&(fields.concat() + kind.salt()))
+}
+
+/// Computes the chk for the given request fields, in the order the client
+/// concatenates them.
+pub fn generate(kind: ChkKind, fields: &[&str]) -> String {
+    xor::encode(&digest(kind, fields), kind.key())
+}
+
+pub fn verify(kind: ChkKind, fields: &[&str], chk: &str) -> bool {
+    let Ok(decoded) = xor::decode(chk, kind.key()) else {
+        return false;
+    };
+
+    decoded.as_bytes().ct_eq(digest(kind, fields).as_bytes()).into()
+}
+
+const LEVEL_SEED_LEN: usize = 50;
+
+/// Samples a level string down to the value the client feeds into a
+/// [`ChkKind::Level`] chk, which is every nth character of the level string
+/// for long levels and the whole string for short ones.
+pub fn level_seed(level_string: &str) -> String {
+    let len = level_string.len();
+
+    if len < LEVEL_SEED_LEN {
+        return level_string.to_owned();
+    }
+
+    level_string
+        .chars()
+        .step_by(len / LEVEL_SEED_LEN)
+        .take(LEVEL_SEED_LEN)
+        .collect()
+}
+
+// Rewards and challenges are prefixed with junk characters the client ignores
+const PREFIX_LEN: usize = 5;
+const PREFIX_CHARS: &[u8] = b"abcdefghijklmnopqrstuvwxyz0123456789";
+
+#[derive(PartialEq, Debug)]
+pub enum ChkError {
+    TooShort,
+    Xor(XorError),
+    Random,
+}
+
+impl fmt::Display for ChkError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::TooShort => write!(
+                f,
+                "chk is shorter than its {PREFIX_LEN} character prefix"
+            ),
+            Self::Xor(_) => write!(f, "chk could not be decoded"),
+            Self::Random => write!(f, "failed to generate a chk prefix"),
+        }
+    }
+}
+
+impl Error for ChkError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Xor(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<XorError> for ChkError {
+    fn from(error: XorError) -> Self {
+        Self::Xor(error)
+    }
+}
+
+/// Decodes a reward or challenge chk sent by the client.
+pub fn decode_prefixed(chk: &str, key: XorKey) -> Result<String, ChkError> {
+    let encoded = chk.get(PREFIX_LEN..).ok_or(ChkError::TooShort)?;
+    Ok(xor::decode(encoded, key)?)
+}
+
+/// Encodes a reward or challenge response body the way the client expects.
+pub fn encode_prefixed(body: &str, key: XorKey) -> Result<String, ChkError> {
+    let mut prefix = [0; PREFIX_LEN];
+    getrandom::fill(&mut prefix).map_err(|_| ChkError::Random)?;
+
+    let prefix: String = prefix
+        .iter()
+        .map(|byte| PREFIX_CHARS[*byte as usize % PREFIX_CHARS.len()] as char)
+        .collect();
+
+    Ok(prefix + &xor::encode(body, key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chk() {
+        let fields = ["babygronk", "deez nuts", "128", "0"];
+        let chk = generate(ChkKind::Comment, &fields);
+
+        assert_eq!(chk, "VwoBCVdWWgFaCVQMAgACAA0BWgEADlUNAgUOBQAIU1gAClACCgwBCA==");
+        assert!(verify(ChkKind::Comment, &fields, &chk));
+        assert!(!verify(ChkKind::Stats, &fields, &chk));
+        assert!(!verify(ChkKind::Comment, &fields[1..], &chk));
+        assert!(!verify(ChkKind::Comment, &fields, "!!!"));
+    }
+
+    #[test]
+    fn test_level_seed() {
+        assert_eq!(level_seed("kS38,1_40"), "kS38,1_40");
+
+        let level_string = "0123456789".repeat(11);
+        let seed = level_seed(&level_string);
+        assert_eq!(seed.len(), LEVEL_SEED_LEN);
+        assert!(seed.starts_with("02468"));
+    }
+
+    #[test]
+    fn test_prefixed_chk() -> Result<(), ChkError> {
+        let chk = encode_prefixed("1:2:3", XorKey::Rewards)?;
+
+        assert_eq!(decode_prefixed(&chk, XorKey::Rewards)?, "1:2:3");
+        assert_eq!(
+            decode_prefixed("abcd", XorKey::Rewards),
+            Err(ChkError::TooShort)
+        );
+        assert_eq!(
+            decode_prefixed("abcde!!!", XorKey::Challenges),
+            Err(ChkError::Xor(XorError::InvalidBase64))
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,216 @@
+// Configurable word filter for player-facing text like names, handles,
+// comments and level titles
+//
+// Word lists are plain text with one entry per line:
+//
+//     # comments and blank lines are ignored
+//     badword       matched anywhere, including inside other words
+//     =badword      only matched as a whole word
+//     !goodword     allowed even though it contains a filtered word
+
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::ops::Range;
+use std::path::Path;
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum FilterMode {
+    Reject,
+    /// Replaces every filtered character with `*`
+    Censor,
+}
+
+#[derive(PartialEq, Debug)]
+pub struct ProfanityError;
+
+impl fmt::Display for ProfanityError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "text contains filtered words")
+    }
+}
+
+impl Error for ProfanityError {}
+
+struct Rule {
+    word: Vec<char>,
+    whole_word: bool,
+}
+
+#[derive(Default)]
+pub struct WordFilter {
+    rules: Vec<Rule>,
+    allowed: Vec<Vec<char>>,
+}
+
+// Maps every character to exactly one character so match positions in the
+// normalized text are also valid in the original
+fn normalize(text: &str) -> Vec<char> {
+    text.chars()
+        .map(|ch| match ch.to_ascii_lowercase() {
+            '4' | '@' => 'a',
+            '8' => 'b',
+            '3' => 'e',
+            '9' => 'g',
+            '1' | '!' | '|' => 'i',
+            '0' => 'o',
+            '5' | '$' => 's',
+            '7' | '+' => 't',
+            ch => ch,
+        })
+        .collect()
+}
+
+fn occurrences<'a>(
+    text: &'a [char],
+    word: &'a [char]
+) -> impl Iterator<Item = Range<usize>> + 'a {
+    text.windows(word.len().max(1))
+        .enumerate()
+        .filter(move |(_, window)| !word.is_empty() && *window == word)
+        .map(move |(start, _)| start..start + word.len())
+}
+
+fn is_whole_word(text: &[char], range: &Range<usize>) -> bool {
+    let is_boundary = |ch: Option<&char>| {
+        ch.is_none_or(|ch| !ch.is_alphanumeric())
+    };
+    let before = range.start.checked_sub(1).and_then(|i| text.get(i));
+
+    is_boundary(before) && is_boundary(text.get(range.end))
+}
+
+impl WordFilter {
+    pub fn parse(list: &str) -> Self {
+        let mut filter = Self::default();
+
+        for line in list.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some(word) = line.strip_prefix('!') {
+                filter.allowed.push(normalize(word));
+            } else if let Some(word) = line.strip_prefix('=') {
+                filter.rules.push(Rule {
+                    word: normalize(word),
+                    whole_word: true,
+                });
+            } else {
+                filter.rules.push(Rule {
+                    word: normalize(line),
+                    whole_word: false,
+                });
+            }
+        }
+
+        filter
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self::parse(&fs::read_to_string(path)?))
+    }
+
+    /// Character ranges of every filtered word in `text`.
+    pub fn find(&self, text: &str) -> Vec<Range<usize>> {
+        let text = normalize(text);
+        let allowed: Vec<Range<usize>> = self
+            .allowed
+            .iter()
+            .flat_map(|word| occurrences(&text, word))
+            .collect();
+        let is_allowed = |range: &Range<usize>| {
+            allowed.iter().any(|allowed| {
+                allowed.start <= range.start && range.end <= allowed.end
+            })
+        };
+
+        let mut matches = Vec::new();
+
+        for rule in &self.rules {
+            for range in occurrences(&text, &rule.word) {
+                if rule.whole_word && !is_whole_word(&text, &range) {
+                    continue;
+                }
+
+                if !is_allowed(&range) {
+                    matches.push(range);
+                }
+            }
+        }
+
+        matches
+    }
+
+    pub fn is_clean(&self, text: &str) -> bool {
+        self.find(text).is_empty()
+    }
+
+    pub fn censor(&self, text: &str) -> String {
+        let matches = self.find(text);
+
+        text.chars()
+            .enumerate()
+            .map(|(i, ch)| {
+                if matches.iter().any(|range| range.contains(&i)) {
+                    '*'
+                } else {
+                    ch
+                }
+            })
+            .collect()
+    }
+
+    pub fn apply(
+        &self,
+        text: &str,
+        mode: FilterMode
+    ) -> Result<String, ProfanityError> {
+        match mode {
+            FilterMode::Reject if !self.is_clean(text) => Err(ProfanityError),
+            FilterMode::Reject => Ok(text.to_owned()),
+            FilterMode::Censor => Ok(self.censor(text)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIST: &str = "
+        # test list
+        nuts
+        =ass
+        !chestnuts
+    ";
+
+    #[test]
+    fn test_word_filter() {
+        let filter = WordFilter::parse(LIST);
+
+        assert!(filter.is_clean("hello there"));
+        assert!(filter.is_clean("chestnuts and grass"));
+        assert!(!filter.is_clean("deez nuts"));
+        assert!(!filter.is_clean("DeezNut5"));
+        assert!(!filter.is_clean("what an a$$"));
+        assert_eq!(filter.find("n u t s deeznuts"), vec![12..16]);
+        assert_eq!(
+            filter.censor("deez NUT$, chestnuts"),
+            "deez ****, chestnuts"
+        );
+    }
+
+    #[test]
+    fn test_filter_modes() {
+        let filter = WordFilter::parse(LIST);
+
+        let reject = |text| filter.apply(text, FilterMode::Reject);
+        let censor = |text| filter.apply(text, FilterMode::Censor);
+
+        assert_eq!(reject("deez nuts"), Err(ProfanityError));
+        assert_eq!(reject("chestnuts"), Ok("chestnuts".to_owned()));
+        assert_eq!(censor("ass"), Ok("***".to_owned()));
+    }
+}
@@ -0,0 +1,106 @@
+// Legacy account password encoding sent by 2.1 and older clients in the `gjp`
+// field, superseded by gjp2
+
+use crate::gjp2::{ClientGjp2, Gjp2, Gjp2Verification};
+use crate::user::{Password, PasswordError};
+use crate::xor::{self, XorError, XorKey};
+use std::error::Error;
+use std::fmt;
+
+#[derive(PartialEq, Debug)]
+pub struct Gjp(Password);
+
+#[derive(PartialEq, Debug)]
+pub enum GjpError {
+    InvalidBase64,
+    InvalidUtf8,
+    InvalidPassword(PasswordError),
+}
+
+impl fmt::Display for GjpError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::InvalidBase64 => write!(f, "gjp is not valid base64"),
+            Self::InvalidUtf8 => write!(f, "decoded gjp is not valid UTF-8"),
+            Self::InvalidPassword(_) => {
+                write!(f, "decoded gjp is not a valid password")
+            }
+        }
+    }
+}
+
+impl From<XorError> for GjpError {
+    fn from(error: XorError) -> Self {
+        match error {
+            XorError::InvalidBase64 => Self::InvalidBase64,
+            XorError::InvalidUtf8 => Self::InvalidUtf8,
+        }
+    }
+}
+
+impl Error for GjpError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::InvalidPassword(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl Gjp {
+    pub fn parse(gjp: &str) -> Result<Self, GjpError> {
+        let password = xor::decode(gjp, XorKey::AccountPassword)?;
+
+        Password::parse(&password)
+            .map(Self)
+            .map_err(GjpError::InvalidPassword)
+    }
+
+    pub fn from_password(password: Password) -> Self {
+        Self(password)
+    }
+
+    pub fn encode(&self) -> String {
+        xor::encode(self.0.as_str(), XorKey::AccountPassword)
+    }
+
+    pub fn password(&self) -> &Password {
+        &self.0
+    }
+
+    pub fn verify(&self, gjp2: &Gjp2) -> Gjp2Verification {
+        gjp2.verify(&self.0)
+    }
+
+    /// The gjp2 a modern client would send for the same password, so legacy
+    /// logins can go through the same path and have their hash upgraded.
+    pub fn to_client_gjp2(&self) -> ClientGjp2 {
+        ClientGjp2::from_password(&self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gjp_parsing() {
+        let gjp = Gjp::from_password(Password::parse("_deez-nuts").unwrap());
+        let encoded = gjp.encode();
+
+        assert_eq!(encoded, "bFNQV0weWUBGRQ==");
+        assert_eq!(Gjp::parse(&encoded), Ok(gjp));
+        assert_eq!(
+            Gjp::parse(encoded.trim_end_matches('='))
+                .unwrap()
+                .password()
+                .as_str(),
+            "_deez-nuts"
+        );
+        assert_eq!(Gjp::parse("!!!"), Err(GjpError::InvalidBase64));
+        assert_eq!(
+            Gjp::parse("EA=="),
+            Err(GjpError::InvalidPassword(PasswordError::Empty))
+        );
+    }
+}
@@ -1,53 +1,435 @@
 use crate::user::Password;
-use bcrypt::{self, DEFAULT_COST, BcryptError};
+use bcrypt::{self, DEFAULT_COST, BcryptError, HashParts};
 use sha1::{Sha1, Digest};
+use subtle::{Choice, ConstantTimeEq};
+use std::error::Error;
+use std::fmt;
 
-const SUFFIX: &'static str = "mI29fmAnxgTs";
+const SUFFIX: &str = "mI29fmAnxgTs";
 
 pub struct Gjp2(String);
 
 impl Gjp2 {
-    fn new(gjp2: &str) -> Self {
+    /// Wraps a bcrypt hash previously produced by [`Gjp2Generator`], e.g.
+    /// one loaded back from storage.
+    pub fn new(gjp2: &str) -> Self {
         Self(gjp2.to_owned())
     }
 
     pub fn as_str(&self) -> &str {
         self.0.as_str()
     }
+
+    /// The bcrypt cost this hash was generated with, `None` if it's malformed.
+    pub fn cost(&self) -> Option<u32> {
+        self.as_str()
+            .parse::<HashParts>()
+            .ok()
+            .map(|parts| parts.get_cost())
+    }
+
+    /// Checks a password against this hash, applying the same salted SHA-1
+    /// pre-hash that [`Gjp2Generator::generate_gjp2`] does.
+    pub fn verify(&self, password: &Password) -> Gjp2Verification {
+        self.verify_client(&ClientGjp2::from_password(password))
+    }
+
+    /// Checks a gjp2 sent by the game client, which is already pre-hashed,
+    /// against this hash.
+    pub fn verify_client(&self, gjp2: &ClientGjp2) -> Gjp2Verification {
+        match bcrypt::verify(gjp2.as_bytes(), self.as_str()) {
+            Ok(true) => Gjp2Verification::Match,
+            Ok(false) => Gjp2Verification::Mismatch,
+            Err(_) => Gjp2Verification::MalformedHash,
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Gjp2Verification {
+    Match,
+    Mismatch,
+    /// The stored hash isn't a valid bcrypt hash, so nothing can match it
+    MalformedHash,
+}
+
+impl Gjp2Verification {
+    pub fn is_match(&self) -> bool {
+        *self == Self::Match
+    }
+}
+
+#[derive(Debug)]
+pub enum Gjp2Error {
+    Bcrypt(BcryptError),
+    MalformedHash(BcryptError),
+    InvalidClientGjp2(ClientGjp2Error),
+    CostNotAllowed(u32),
 }
 
-pub struct Gjp2Error(pub BcryptError);
+impl fmt::Display for Gjp2Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Bcrypt(_) => write!(f, "bcrypt hashing failed"),
+            Self::MalformedHash(_) => write!(f, "malformed bcrypt hash"),
+            Self::InvalidClientGjp2(_) => write!(f, "invalid client gjp2"),
+            Self::CostNotAllowed(cost) => write!(
+                f,
+                "bcrypt cost {cost} is outside of {COST_MIN}..={COST_MAX}"
+            ),
+        }
+    }
+}
+
+impl Error for Gjp2Error {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Bcrypt(error) | Self::MalformedHash(error) => Some(error),
+            Self::InvalidClientGjp2(error) => Some(error),
+            Self::CostNotAllowed(_) => None,
+        }
+    }
+}
 
 impl From<BcryptError> for Gjp2Error {
     fn from(error: BcryptError) -> Self {
-        Self(error)
+        match error {
+            BcryptError::CostNotAllowed(cost) => Self::CostNotAllowed(cost),
+            BcryptError::InvalidCost(_)
+            | BcryptError::InvalidPrefix(_)
+            | BcryptError::InvalidHash(_)
+            | BcryptError::InvalidSaltLen(_)
+            | BcryptError::InvalidBase64(_) => Self::MalformedHash(error),
+            _ => Self::Bcrypt(error),
+        }
+    }
+}
+
+impl From<ClientGjp2Error> for Gjp2Error {
+    fn from(error: ClientGjp2Error) -> Self {
+        Self::InvalidClientGjp2(error)
+    }
+}
+
+const SHA1_LEN: usize = 20;
+
+fn salted_sha1(digest: &mut Sha1, password: &Password) -> [u8; SHA1_LEN] {
+    digest.update(password.as_str().to_owned() + SUFFIX);
+    let sha1_hash = digest.clone().finalize();
+    digest.reset();
+    sha1_hash.into()
+}
+
+/// The `gjp2` field as sent by the game client: the hex encoded SHA-1 of the
+/// password followed by [`SUFFIX`].
+#[derive(Debug, Clone)]
+pub struct ClientGjp2([u8; SHA1_LEN]);
+
+impl ConstantTimeEq for ClientGjp2 {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        self.0.ct_eq(&other.0)
+    }
+}
+
+// A gjp2 is as good as a password, don't leak how much of it matched
+impl PartialEq for ClientGjp2 {
+    fn eq(&self, other: &Self) -> bool {
+        self.ct_eq(other).into()
+    }
+}
+
+impl Eq for ClientGjp2 {}
+
+#[derive(PartialEq, Debug)]
+pub enum ClientGjp2Error {
+    Empty,
+    WrongLength,
+    InvalidHex,
+}
+
+impl fmt::Display for ClientGjp2Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "gjp2 is empty"),
+            Self::WrongLength => write!(
+                f,
+                "gjp2 must be exactly {} hex digits",
+                SHA1_LEN * 2
+            ),
+            Self::InvalidHex => write!(f, "gjp2 contains non-hex characters"),
+        }
+    }
+}
+
+impl Error for ClientGjp2Error {}
+
+impl ClientGjp2 {
+    pub fn parse(gjp2: &str) -> Result<Self, ClientGjp2Error> {
+        if gjp2.is_empty() {
+            return Err(ClientGjp2Error::Empty);
+        }
+
+        if gjp2.len() != SHA1_LEN * 2 {
+            return Err(ClientGjp2Error::WrongLength);
+        }
+
+        let mut bytes = [0; SHA1_LEN];
+
+        for (byte, pair) in bytes.iter_mut().zip(gjp2.as_bytes().chunks(2)) {
+            let high = hex_digit(pair[0]).ok_or(ClientGjp2Error::InvalidHex)?;
+            let low = hex_digit(pair[1]).ok_or(ClientGjp2Error::InvalidHex)?;
+            *byte = high << 4 | low;
+        }
+
+        Ok(Self(bytes))
+    }
+
+    /// Computes the gjp2 the game client would send for this password.
+    pub fn from_password(password: &Password) -> Self {
+        Self(salted_sha1(&mut Sha1::new(), password))
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn to_hex(&self) -> String {
+        self.0.iter().map(|byte| format!("{byte:02x}")).collect()
     }
 }
 
-fn verify_gjp2(text: &str, gjp2: Gjp2) -> Result<bool, Gjp2Error> {
-    Ok(bcrypt::verify(text, gjp2.as_str())?)
+fn hex_digit(digit: u8) -> Option<u8> {
+    (digit as char).to_digit(16).map(|value| value as u8)
 }
 
+const COST_MIN: u32 = 4;
+const COST_MAX: u32 = 31;
+
+// Anything works as long as it's hashed with the generator's cost, the dummy
+// hash only exists to be verified against
+const DUMMY_SALT: [u8; 16] = *b"geometry-deez-gd";
+
 pub struct Gjp2Generator {
     digest: Sha1,
+    cost: u32,
+    dummy: Option<Gjp2>,
 }
 
 impl Gjp2Generator {
     pub fn new(digest: Sha1) -> Self {
         Self {
             digest,
+            cost: DEFAULT_COST,
+            dummy: None,
+        }
+    }
+
+    pub fn with_cost(digest: Sha1, cost: u32) -> Result<Self, Gjp2Error> {
+        if !(COST_MIN..=COST_MAX).contains(&cost) {
+            return Err(Gjp2Error::CostNotAllowed(cost));
+        }
+
+        Ok(Self {
+            digest,
+            cost,
+            dummy: None,
+        })
+    }
+
+    pub fn cost(&self) -> u32 {
+        self.cost
+    }
+
+    /// Whether a stored hash was generated with a different cost than this
+    /// generator's and should be replaced on the user's next login.
+    pub fn needs_rehash(&self, gjp2: &Gjp2) -> bool {
+        gjp2.cost() != Some(self.cost)
+    }
+
+    /// Verifies a login and, if it succeeded and the stored hash
+    /// [needs rehashing](Self::needs_rehash), also returns a fresh hash for
+    /// the storage layer to replace it with.
+    pub fn verify_and_rehash(
+        &mut self,
+        stored: &Gjp2,
+        gjp2: &ClientGjp2
+    ) -> Result<(Gjp2Verification, Option<Gjp2>), Gjp2Error> {
+        let verification = stored.verify_client(gjp2);
+
+        if !verification.is_match() || !self.needs_rehash(stored) {
+            return Ok((verification, None));
+        }
+
+        Ok((verification, Some(self.generate_gjp2_from_client(gjp2)?)))
+    }
+
+    /// Like [`verify_and_rehash`](Self::verify_and_rehash), but takes `None`
+    /// for accounts that don't exist and still runs a bcrypt verification
+    /// of the same cost against a dummy hash, so response times don't reveal
+    /// which names are registered.
+    pub fn verify_login(
+        &mut self,
+        stored: Option<&Gjp2>,
+        gjp2: &ClientGjp2
+    ) -> Result<(Gjp2Verification, Option<Gjp2>), Gjp2Error> {
+        if let Some(stored) = stored {
+            return self.verify_and_rehash(stored, gjp2);
         }
+
+        let dummy = match &self.dummy {
+            Some(dummy) => dummy,
+            None => {
+                let parts = bcrypt::hash_with_salt(
+                    [0; SHA1_LEN],
+                    self.cost,
+                    DUMMY_SALT
+                )?;
+                self.dummy.insert(Gjp2::new(&parts.to_string()))
+            }
+        };
+
+        dummy.verify_client(gjp2);
+        Ok((Gjp2Verification::Mismatch, None))
     }
 
     pub fn generate_gjp2(
         &mut self,
         password: Password
     ) -> Result<Gjp2, Gjp2Error> {
-        self.digest.update(password.as_str().to_owned() + SUFFIX);
-        let sha1_hash = self.digest.clone().finalize();
-        self.digest.reset();
+        let sha1_hash = salted_sha1(&mut self.digest, &password);
+        let bcrypt_hash = bcrypt::hash(sha1_hash, self.cost)?;
+        Ok(Gjp2::new(bcrypt_hash.as_str()))
+    }
 
-        let bcrypt_hash = bcrypt::hash(sha1_hash, DEFAULT_COST)?;
+    /// Hashes a gjp2 sent by the game client, for when the plaintext password
+    /// is never seen by the server.
+    pub fn generate_gjp2_from_client(
+        &mut self,
+        gjp2: &ClientGjp2
+    ) -> Result<Gjp2, Gjp2Error> {
+        let bcrypt_hash = bcrypt::hash(gjp2.as_bytes(), self.cost)?;
         Ok(Gjp2::new(bcrypt_hash.as_str()))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account::test_generator;
+
+    #[test]
+    fn test_gjp2_verification() -> Result<(), Gjp2Error> {
+        let password = Password::parse("_deez-nuts").unwrap();
+        let gjp2 = test_generator(4)
+            .generate_gjp2(Password::parse("_deez-nuts").unwrap())?;
+
+        assert_eq!(gjp2.verify(&password), Gjp2Verification::Match);
+        assert_eq!(
+            gjp2.verify(&Password::parse("deez-nuts_").unwrap()),
+            Gjp2Verification::Mismatch
+        );
+        assert_eq!(
+            Gjp2::new("not a bcrypt hash").verify(&password),
+            Gjp2Verification::MalformedHash
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_client_gjp2() -> Result<(), Gjp2Error> {
+        // sha1("_deez-nuts" + SUFFIX)
+        let hex = "ce2ce5e449abd3663b7465a17c362ba2a3f455d6";
+        let password = Password::parse("_deez-nuts").unwrap();
+        let gjp2 = ClientGjp2::from_password(&password);
+
+        assert_eq!(ClientGjp2::parse(""), Err(ClientGjp2Error::Empty));
+        assert_eq!(ClientGjp2::parse("abc"), Err(ClientGjp2Error::WrongLength));
+        assert_eq!(
+            ClientGjp2::parse(&hex.replace('a', "z")),
+            Err(ClientGjp2Error::InvalidHex)
+        );
+        assert_eq!(gjp2.to_hex(), hex);
+        assert_eq!(ClientGjp2::parse(&hex.to_uppercase()), Ok(gjp2.clone()));
+
+        let stored = test_generator(4).generate_gjp2_from_client(&gjp2)?;
+        assert!(stored.verify_client(&gjp2).is_match());
+        assert!(stored.verify(&password).is_match());
+        Ok(())
+    }
+
+    #[test]
+    fn test_gjp2_rehash() -> Result<(), Gjp2Error> {
+        let password = Password::parse("_deez-nuts").unwrap();
+        let gjp2 = ClientGjp2::from_password(&password);
+        let mut old = test_generator(4);
+        let mut new = test_generator(5);
+        let stored = old.generate_gjp2_from_client(&gjp2)?;
+
+        assert!(matches!(
+            Gjp2Generator::with_cost(Sha1::new(), 3),
+            Err(Gjp2Error::CostNotAllowed(3))
+        ));
+        assert_eq!(stored.cost(), Some(4));
+        assert_eq!(Gjp2::new("").cost(), None);
+        assert!(!old.needs_rehash(&stored));
+        assert!(new.needs_rehash(&stored));
+
+        let (verification, rehashed) = old.verify_and_rehash(&stored, &gjp2)?;
+        assert!(verification.is_match());
+        assert!(rehashed.is_none());
+
+        let (verification, rehashed) = new.verify_and_rehash(&stored, &gjp2)?;
+        let rehashed = rehashed.unwrap();
+        assert!(verification.is_match());
+        assert_eq!(rehashed.cost(), Some(5));
+        assert!(rehashed.verify_client(&gjp2).is_match());
+
+        let password = Password::parse("deez-nuts_").unwrap();
+        let other = ClientGjp2::from_password(&password);
+        let (verification, rehashed) = new.verify_and_rehash(&stored, &other)?;
+        assert_eq!(verification, Gjp2Verification::Mismatch);
+        assert!(rehashed.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_gjp2_login() -> Result<(), Gjp2Error> {
+        let password = Password::parse("_deez-nuts").unwrap();
+        let gjp2 = ClientGjp2::from_password(&password);
+        let mut generator = test_generator(4);
+        let stored = generator.generate_gjp2_from_client(&gjp2)?;
+
+        let (verification, _) = generator.verify_login(Some(&stored), &gjp2)?;
+        assert!(verification.is_match());
+
+        for _ in 0..2 {
+            let (verification, rehashed) = generator.verify_login(None, &gjp2)?;
+            assert_eq!(verification, Gjp2Verification::Mismatch);
+            assert!(rehashed.is_none());
+        }
+
+        assert_eq!(generator.dummy.as_ref().and_then(Gjp2::cost), Some(4));
+        assert_ne!(
+            gjp2,
+            ClientGjp2::from_password(&Password::parse("deez-nuts_").unwrap())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_gjp2_error() {
+        let error = Gjp2Error::from(BcryptError::InvalidPrefix("1a".into()));
+        assert!(matches!(error, Gjp2Error::MalformedHash(_)));
+        assert!(error.source().is_some());
+
+        let error: Box<dyn Error> = ClientGjp2::parse("xyz")
+            .map_err(Gjp2Error::from)
+            .unwrap_err()
+            .into();
+        assert_eq!(error.to_string(), "invalid client gjp2");
+        assert_eq!(
+            error.source().unwrap().to_string(),
+            "gjp2 must be exactly 40 hex digits"
+        );
+    }
+}
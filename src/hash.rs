@@ -0,0 +1,189 @@
+// Hashes the client expects as trailing `#` segments of level, map pack,
+// gauntlet and download responses, it refuses any response they don't match
+
+use crate::chk::sha1_hex;
+
+const SALT: &str = "xI25fpAapCQg";
+
+fn salted_hash(input: &str) -> String {
+    sha1_hex(&(input.to_owned() + SALT))
+}
+
+fn first_and_last_digits(id: u64) -> String {
+    let id = id.to_string();
+    let first = &id[..1];
+    let last = &id[id.len() - 1..];
+    format!("{first}{last}")
+}
+
+pub struct LevelHashInfo {
+    pub id: u64,
+    pub stars: u32,
+    pub coins_verified: bool,
+}
+
+/// Hash for level search results.
+pub fn level_list_hash(levels: &[LevelHashInfo]) -> String {
+    let input: String = levels
+        .iter()
+        .map(|level| {
+            format!(
+                "{}{}{}",
+                first_and_last_digits(level.id),
+                level.stars,
+                level.coins_verified as u8
+            )
+        })
+        .collect();
+
+    salted_hash(&input)
+}
+
+pub struct MapPackHashInfo {
+    pub id: u64,
+    pub stars: u32,
+    pub coins: u32,
+}
+
+pub fn map_pack_hash(packs: &[MapPackHashInfo]) -> String {
+    let input: String = packs
+        .iter()
+        .map(|pack| {
+            format!(
+                "{}{}{}",
+                first_and_last_digits(pack.id),
+                pack.stars,
+                pack.coins
+            )
+        })
+        .collect();
+
+    salted_hash(&input)
+}
+
+pub struct GauntletHashInfo {
+    pub id: u64,
+    pub level_ids: Vec<u64>,
+}
+
+pub fn gauntlet_hash(gauntlets: &[GauntletHashInfo]) -> String {
+    let input: String = gauntlets
+        .iter()
+        .map(|gauntlet| {
+            let level_ids: Vec<String> = gauntlet
+                .level_ids
+                .iter()
+                .map(u64::to_string)
+                .collect();
+            format!("{}{}", gauntlet.id, level_ids.join(","))
+        })
+        .collect();
+
+    salted_hash(&input)
+}
+
+const LEVEL_STRING_SAMPLES: usize = 40;
+
+/// First hash of a level download response, sampled from the level string.
+pub fn level_string_hash(level_string: &str) -> String {
+    let bytes = level_string.as_bytes();
+
+    if bytes.is_empty() {
+        return salted_hash("");
+    }
+
+    let step = bytes.len() / LEVEL_STRING_SAMPLES;
+    let input: String = (0..LEVEL_STRING_SAMPLES)
+        .map(|i| bytes[i * step] as char)
+        .collect();
+
+    salted_hash(&input)
+}
+
+pub struct DownloadHashInfo {
+    pub user_id: u64,
+    pub stars: u32,
+    pub demon: bool,
+    pub level_id: u64,
+    pub coins_verified: bool,
+    pub featured: bool,
+    /// The copy password exactly as sent in the response, e.g. `0` or
+    /// `1123456`
+    pub password: String,
+    /// Daily or weekly ID, 0 for regular downloads
+    pub feature_id: u64,
+}
+
+/// Second hash of a level download response.
+pub fn download_hash(info: &DownloadHashInfo) -> String {
+    let input = format!(
+        "{},{},{},{},{},{},{},{}",
+        info.user_id,
+        info.stars,
+        info.demon as u8,
+        info.level_id,
+        info.coins_verified as u8,
+        info.featured as u8,
+        info.password,
+        info.feature_id
+    );
+
+    salted_hash(&input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_list_hashes() {
+        let levels = [
+            LevelHashInfo { id: 128, stars: 10, coins_verified: true },
+            LevelHashInfo { id: 7, stars: 0, coins_verified: false },
+        ];
+        let packs = [MapPackHashInfo { id: 12, stars: 4, coins: 1 }];
+        let gauntlets = [
+            GauntletHashInfo { id: 1, level_ids: vec![27732941, 28200611] },
+        ];
+
+        assert_eq!(
+            level_list_hash(&levels),
+            "de91f9cdb26ab62c2bcd1b93636d5c984f108001"
+        );
+        assert_eq!(
+            map_pack_hash(&packs),
+            "5ab7b5c9fa98cb6caabe53bd8b03a6faa2b1e280"
+        );
+        assert_eq!(
+            gauntlet_hash(&gauntlets),
+            "3e3790fc02937cc51067b960c1dbc43c19e0291b"
+        );
+    }
+
+    #[test]
+    fn test_download_hashes() {
+        let info = DownloadHashInfo {
+            user_id: 16,
+            stars: 10,
+            demon: true,
+            level_id: 128,
+            coins_verified: true,
+            featured: false,
+            password: "1123456".to_owned(),
+            feature_id: 0,
+        };
+
+        assert_eq!(
+            download_hash(&info),
+            "9c8f5c25633a031f2efd1bcd97430975b306957a"
+        );
+        assert_eq!(
+            level_string_hash(&"0123456789".repeat(8)),
+            "c8d1d6df520d5e8a4cc751df9a3292557b72e55c"
+        );
+        assert_eq!(
+            level_string_hash("kS38"),
+            "23ae193823050e7f06b505416906c706216dbc86"
+        );
+    }
+}
@@ -0,0 +1,502 @@
+// Automatic leaderboard bans for stat submissions no legitimate player could
+// have sent, with every decision kept for moderators to go through
+
+use crate::account::AccountStore;
+use crate::stats::{StatLimits, SUBMITTED_STATS};
+use crate::timestamp::Timestamp;
+use crate::user::{Ban, Stat, Stats, User};
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt;
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Condition {
+    /// The stat is above the given total, e.g. more demons than there are
+    /// rated demons
+    Exceeds(Stat, u32),
+    /// The stat grew by more than the given amount in one submission
+    IncreasesBy(Stat, u32),
+}
+
+impl Condition {
+    fn matches(&self, current: &Stats, submitted: &Stats) -> bool {
+        match *self {
+            Self::Exceeds(stat, total) => submitted.get(stat) > total,
+            Self::IncreasesBy(stat, amount) => {
+                submitted.get(stat).saturating_sub(current.get(stat)) > amount
+            }
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum BanAction {
+    Apply,
+    /// Leaves the decision to a moderator
+    Propose,
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct BanRule {
+    pub condition: Condition,
+    pub action: BanAction,
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum BanEvent {
+    Banned(Condition),
+    Proposed(Condition),
+    /// A rule matched but the account is on the override list
+    Exempted(Condition),
+    Confirmed,
+    Dismissed,
+    Lifted,
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct AuditEntry {
+    pub account_id: u64,
+    pub at: Timestamp,
+    pub event: BanEvent,
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct BanProposal {
+    pub id: u64,
+    pub account_id: u64,
+    pub at: Timestamp,
+    pub condition: Condition,
+}
+
+#[derive(PartialEq, Debug)]
+pub enum BanVerdict {
+    Clean,
+    AlreadyBanned,
+    Exempt(Condition),
+    Banned(Condition),
+    Proposed(u64),
+}
+
+#[derive(PartialEq, Debug)]
+pub enum BanError {
+    UnknownProposal,
+    UnknownAccount,
+}
+
+impl fmt::Display for BanError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::UnknownProposal => write!(f, "ban proposal does not exist"),
+            Self::UnknownAccount => write!(f, "account does not exist"),
+        }
+    }
+}
+
+impl Error for BanError {}
+
+/// Lets the audit trail, pending proposals and overrides live wherever the
+/// rest of the accounts do.
+pub trait BanLog {
+    fn audit(&mut self, entry: AuditEntry);
+    /// The event of the account's most recent audit entry.
+    fn last_event(&self, account_id: u64) -> Option<BanEvent>;
+    fn is_exempt(&self, account_id: u64) -> bool;
+    fn set_exempt(&mut self, account_id: u64, exempt: bool);
+    fn next_proposal_id(&mut self) -> u64;
+    fn propose(&mut self, proposal: BanProposal);
+    fn proposal(&self, proposal_id: u64) -> Option<BanProposal>;
+    /// ID of the account's pending proposal for the same condition.
+    fn pending_proposal(
+        &self,
+        account_id: u64,
+        condition: &Condition
+    ) -> Option<u64>;
+    fn take_proposal(&mut self, proposal_id: u64) -> Option<BanProposal>;
+    /// Remembers that a moderator dismissed the condition for the account.
+    fn dismiss(&mut self, account_id: u64, condition: Condition);
+    fn is_dismissed(&self, account_id: u64, condition: &Condition) -> bool;
+}
+
+#[derive(Default)]
+pub struct MemoryBanLog {
+    audit: Vec<AuditEntry>,
+    overrides: HashSet<u64>,
+    proposals: Vec<BanProposal>,
+    proposal_count: u64,
+    dismissed: Vec<(u64, Condition)>,
+}
+
+impl MemoryBanLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn entries(&self) -> &[AuditEntry] {
+        &self.audit
+    }
+
+    pub fn proposals(&self) -> &[BanProposal] {
+        &self.proposals
+    }
+}
+
+impl BanLog for MemoryBanLog {
+    fn audit(&mut self, entry: AuditEntry) {
+        self.audit.push(entry);
+    }
+
+    fn last_event(&self, account_id: u64) -> Option<BanEvent> {
+        self.audit
+            .iter()
+            .rev()
+            .find(|entry| entry.account_id == account_id)
+            .map(|entry| entry.event)
+    }
+
+    fn is_exempt(&self, account_id: u64) -> bool {
+        self.overrides.contains(&account_id)
+    }
+
+    fn set_exempt(&mut self, account_id: u64, exempt: bool) {
+        if exempt {
+            self.overrides.insert(account_id);
+        } else {
+            self.overrides.remove(&account_id);
+        }
+    }
+
+    fn next_proposal_id(&mut self) -> u64 {
+        self.proposal_count += 1;
+        self.proposal_count
+    }
+
+    fn propose(&mut self, proposal: BanProposal) {
+        self.proposals.push(proposal);
+    }
+
+    fn proposal(&self, proposal_id: u64) -> Option<BanProposal> {
+        self.proposals
+            .iter()
+            .find(|proposal| proposal.id == proposal_id)
+            .copied()
+    }
+
+    fn pending_proposal(
+        &self,
+        account_id: u64,
+        condition: &Condition
+    ) -> Option<u64> {
+        self.proposals
+            .iter()
+            .find(|proposal| {
+                proposal.account_id == account_id
+                    && proposal.condition == *condition
+            })
+            .map(|proposal| proposal.id)
+    }
+
+    fn take_proposal(&mut self, proposal_id: u64) -> Option<BanProposal> {
+        let index = self
+            .proposals
+            .iter()
+            .position(|proposal| proposal.id == proposal_id)?;
+
+        Some(self.proposals.remove(index))
+    }
+
+    fn dismiss(&mut self, account_id: u64, condition: Condition) {
+        self.dismissed.push((account_id, condition));
+    }
+
+    fn is_dismissed(&self, account_id: u64, condition: &Condition) -> bool {
+        self.dismissed.contains(&(account_id, *condition))
+    }
+}
+
+pub struct BanRules<L: BanLog> {
+    rules: Vec<BanRule>,
+    log: L,
+}
+
+impl<L: BanLog> BanRules<L> {
+    pub fn new(rules: Vec<BanRule>, log: L) -> Self {
+        Self { rules, log }
+    }
+
+    /// One rule per submitted stat, matching anything above the maxima the
+    /// stats are validated against.
+    pub fn from_limits(limits: &StatLimits, action: BanAction, log: L) -> Self {
+        let rules = SUBMITTED_STATS
+            .into_iter()
+            .map(|stat| BanRule {
+                condition: Condition::Exceeds(stat, limits.maximum.get(stat)),
+                action,
+            })
+            .collect();
+
+        Self::new(rules, log)
+    }
+
+    pub fn add_rule(&mut self, rule: BanRule) -> &mut Self {
+        self.rules.push(rule);
+        self
+    }
+
+    pub fn log(&self) -> &L {
+        &self.log
+    }
+
+    /// Keeps the rules from ever banning the account, for players a
+    /// moderator has vouched for.
+    pub fn exempt(&mut self, account_id: u64) -> &mut Self {
+        self.log.set_exempt(account_id, true);
+        self
+    }
+
+    pub fn remove_exemption(&mut self, account_id: u64) -> &mut Self {
+        self.log.set_exempt(account_id, false);
+        self
+    }
+
+    pub fn is_exempt(&self, account_id: u64) -> bool {
+        self.log.is_exempt(account_id)
+    }
+
+    fn audit(&mut self, account_id: u64, now: Timestamp, event: BanEvent) {
+        self.log.audit(AuditEntry {
+            account_id,
+            at: now,
+            event,
+        });
+    }
+
+    /// Runs the rules against a submission before it is applied, rules that
+    /// apply a ban take precedence over ones that only propose it. Conditions
+    /// a moderator dismissed for the account are skipped.
+    pub fn evaluate(
+        &mut self,
+        user: &mut User,
+        submitted: &Stats,
+        now: Timestamp
+    ) -> BanVerdict {
+        if user.ban.is_leaderboard_banned() {
+            return BanVerdict::AlreadyBanned;
+        }
+
+        let matched = |action: BanAction| {
+            self.rules
+                .iter()
+                .filter(|rule| rule.action == action)
+                .filter(|rule| !self.log.is_dismissed(user.id, &rule.condition))
+                .find(|rule| rule.condition.matches(&user.stats, submitted))
+                .map(|rule| rule.condition)
+        };
+        let applied = matched(BanAction::Apply);
+        let proposed = matched(BanAction::Propose);
+
+        let Some(condition) = applied.or(proposed) else {
+            return BanVerdict::Clean;
+        };
+
+        if self.is_exempt(user.id) {
+            let event = BanEvent::Exempted(condition);
+
+            // Only the first of the client's resubmissions is worth keeping
+            if self.log.last_event(user.id) != Some(event) {
+                self.audit(user.id, now, event);
+            }
+            return BanVerdict::Exempt(condition);
+        }
+
+        if applied.is_some() {
+            user.ban = user.ban.combine(Ban::LeaderboardBan);
+            self.audit(user.id, now, BanEvent::Banned(condition));
+            return BanVerdict::Banned(condition);
+        }
+
+        // The client resubmits the same stats every time the menu is opened
+        if let Some(id) = self.log.pending_proposal(user.id, &condition) {
+            return BanVerdict::Proposed(id);
+        }
+
+        let id = self.log.next_proposal_id();
+        self.log.propose(BanProposal {
+            id,
+            account_id: user.id,
+            at: now,
+            condition,
+        });
+        self.audit(user.id, now, BanEvent::Proposed(condition));
+        BanVerdict::Proposed(id)
+    }
+
+    pub fn confirm(
+        &mut self,
+        store: &mut impl AccountStore,
+        proposal_id: u64,
+        now: Timestamp
+    ) -> Result<(), BanError> {
+        let proposal = self
+            .log
+            .proposal(proposal_id)
+            .ok_or(BanError::UnknownProposal)?;
+        let account = store
+            .find_by_id_mut(proposal.account_id)
+            .ok_or(BanError::UnknownAccount)?;
+
+        self.log.take_proposal(proposal_id);
+        account.user.ban = account.user.ban.combine(Ban::LeaderboardBan);
+        self.audit(proposal.account_id, now, BanEvent::Confirmed);
+        Ok(())
+    }
+
+    /// Keeps the condition from being proposed for the account again.
+    pub fn dismiss(
+        &mut self,
+        proposal_id: u64,
+        now: Timestamp
+    ) -> Result<(), BanError> {
+        let proposal = self
+            .log
+            .take_proposal(proposal_id)
+            .ok_or(BanError::UnknownProposal)?;
+
+        self.log.dismiss(proposal.account_id, proposal.condition);
+        self.audit(proposal.account_id, now, BanEvent::Dismissed);
+        Ok(())
+    }
+
+    /// Lifts a leaderboard ban, creator bans stay in place. Exempt the
+    /// account as well to keep the rules from banning it again.
+    pub fn lift(&mut self, user: &mut User, now: Timestamp) {
+        user.ban = user.ban.lift(Ban::LeaderboardBan);
+        self.audit(user.id, now, BanEvent::Lifted);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account::{test_account, MemoryAccountStore};
+    use crate::user::test_user;
+
+    fn rules() -> BanRules<MemoryBanLog> {
+        let mut rules = BanRules::new(
+            vec![BanRule {
+                condition: Condition::Exceeds(Stat::Demons, 10),
+                action: BanAction::Apply,
+            }],
+            MemoryBanLog::new()
+        );
+        rules.add_rule(BanRule {
+            condition: Condition::IncreasesBy(Stat::Stars, 500),
+            action: BanAction::Propose,
+        });
+        rules
+    }
+
+    #[test]
+    fn test_automatic_bans() {
+        let mut rules = rules();
+        let mut user = test_user(1);
+        let now = Timestamp::from_unix(1);
+        let mut stats = Stats {
+            stars: 500,
+            demons: 10,
+            ..Stats::default()
+        };
+
+        let verdict = rules.evaluate(&mut user, &stats, now);
+        assert_eq!(verdict, BanVerdict::Clean);
+
+        user.ban = Ban::CreatorBan;
+        stats.demons = 11;
+        let demons = Condition::Exceeds(Stat::Demons, 10);
+        let verdict = rules.evaluate(&mut user, &stats, now);
+        assert_eq!(verdict, BanVerdict::Banned(demons));
+        assert_eq!(user.ban, Ban::LeaderboardAndCreatorBan);
+
+        let verdict = rules.evaluate(&mut user, &stats, now);
+        assert_eq!(verdict, BanVerdict::AlreadyBanned);
+
+        rules.lift(&mut user, now);
+        rules.exempt(1);
+        assert_eq!(user.ban, Ban::CreatorBan);
+        for _ in 0..2 {
+            let verdict = rules.evaluate(&mut user, &stats, now);
+            assert_eq!(verdict, BanVerdict::Exempt(demons));
+        }
+        assert_eq!(user.ban, Ban::CreatorBan);
+
+        let events: Vec<BanEvent> =
+            rules.log().entries().iter().map(|entry| entry.event).collect();
+        assert_eq!(
+            events,
+            [
+                BanEvent::Banned(demons),
+                BanEvent::Lifted,
+                BanEvent::Exempted(demons),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ban_proposals() -> Result<(), BanError> {
+        let mut rules = rules();
+        let mut store = MemoryAccountStore::new();
+        let now = Timestamp::from_unix(1);
+        let stats = Stats {
+            stars: 501,
+            ..Stats::default()
+        };
+
+        for id in [1, 2] {
+            let mut user = test_user(id);
+            let verdict = rules.evaluate(&mut user, &stats, now);
+            assert_eq!(verdict, BanVerdict::Proposed(id));
+            let verdict = rules.evaluate(&mut user, &stats, now);
+            assert_eq!(verdict, BanVerdict::Proposed(id));
+            assert_eq!(user.ban, Ban::None);
+            store.insert(test_account(user));
+        }
+        assert_eq!(rules.log().proposals().len(), 2);
+
+        rules.confirm(&mut store, 1, now)?;
+        rules.dismiss(2, now)?;
+        assert_eq!(store.accounts()[0].user.ban, Ban::LeaderboardBan);
+        assert_eq!(store.accounts()[1].user.ban, Ban::None);
+        assert!(rules.log().proposals().is_empty());
+        assert_eq!(rules.dismiss(2, now), Err(BanError::UnknownProposal));
+        assert_eq!(rules.log().entries().len(), 4);
+
+        let user = &mut store.find_by_id_mut(2).unwrap().user;
+        let verdict = rules.evaluate(user, &stats, now);
+        assert_eq!(verdict, BanVerdict::Clean);
+        assert!(rules.log().proposals().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_confirm_unknown_account() -> Result<(), BanError> {
+        let mut rules = rules();
+        let mut store = MemoryAccountStore::new();
+        let mut user = test_user(1);
+        let now = Timestamp::from_unix(1);
+        let stats = Stats {
+            stars: 501,
+            ..Stats::default()
+        };
+
+        rules.evaluate(&mut user, &stats, now);
+        assert_eq!(
+            rules.confirm(&mut store, 1, now),
+            Err(BanError::UnknownAccount)
+        );
+        assert_eq!(rules.log().proposals().len(), 1);
+
+        store.insert(test_account(user));
+        rules.confirm(&mut store, 1, now)?;
+        assert_eq!(store.accounts()[0].user.ban, Ban::LeaderboardBan);
+        Ok(())
+    }
+}
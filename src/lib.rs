@@ -1,2 +1,19 @@
+pub mod account;
+pub mod activation;
+pub mod chk;
+pub mod filter;
+pub mod gjp;
 pub mod gjp2;
+pub mod hash;
+pub mod leaderboard_ban;
+pub mod login;
+pub mod password_change;
+pub mod register;
+pub mod session;
+pub mod settings;
+pub mod stats;
+pub mod throttle;
+pub mod timestamp;
+pub mod token;
 pub mod user;
+pub mod xor;
@@ -0,0 +1,313 @@
+// Account login, mirroring the responses of `accounts/loginGJAccount.php`
+
+use crate::account::AccountStore;
+use crate::gjp2::{ClientGjp2, Gjp2Error, Gjp2Generator};
+use crate::throttle::{Lockout, LoginThrottle};
+use crate::timestamp::Timestamp;
+use crate::user::{Name, Password};
+use std::error::Error;
+use std::fmt;
+use std::net::IpAddr;
+
+pub enum LoginCredential {
+    Gjp2(ClientGjp2),
+    /// Plaintext password sent by 2.1 and older clients
+    Password(Password),
+}
+
+impl LoginCredential {
+    fn to_client_gjp2(&self) -> ClientGjp2 {
+        match self {
+            Self::Gjp2(gjp2) => gjp2.clone(),
+            Self::Password(password) => ClientGjp2::from_password(password),
+        }
+    }
+}
+
+pub struct LoginRequest<'a> {
+    pub user_name: &'a str,
+    pub credential: LoginCredential,
+    pub steam_id: Option<u64>,
+    pub ip: IpAddr,
+}
+
+#[derive(PartialEq, Debug)]
+pub struct LoginResponse {
+    pub account_id: u64,
+    pub user_id: u64,
+}
+
+impl fmt::Display for LoginResponse {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{},{}", self.account_id, self.user_id)
+    }
+}
+
+#[derive(Debug)]
+pub enum LoginError {
+    Failed,
+    NotActivated,
+    Disabled,
+    LinkedToDifferentSteamAccount,
+    Throttled(Lockout),
+    Gjp2(Gjp2Error),
+}
+
+impl LoginError {
+    /// The response code the client shows the matching dialog for.
+    pub fn code(&self) -> i32 {
+        match self {
+            Self::Failed | Self::Gjp2(_) => -1,
+            Self::NotActivated => -11,
+            // The client has no dialog for this, other servers settled on
+            // reporting it as disabled
+            Self::Disabled | Self::Throttled(_) => -12,
+            Self::LinkedToDifferentSteamAccount => -13,
+        }
+    }
+}
+
+impl fmt::Display for LoginError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Failed => write!(f, "wrong name or password"),
+            Self::NotActivated => write!(f, "account is not activated"),
+            Self::Disabled => write!(f, "account is disabled"),
+            Self::LinkedToDifferentSteamAccount => {
+                write!(f, "account is linked to a different steam account")
+            }
+            Self::Throttled(_) => write!(f, "too many failed login attempts"),
+            Self::Gjp2(_) => write!(f, "failed to rehash gjp2"),
+        }
+    }
+}
+
+impl Error for LoginError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Gjp2(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<Gjp2Error> for LoginError {
+    fn from(error: Gjp2Error) -> Self {
+        Self::Gjp2(error)
+    }
+}
+
+pub struct Authenticator<T: LoginThrottle> {
+    generator: Gjp2Generator,
+    throttle: T,
+}
+
+impl<T: LoginThrottle> Authenticator<T> {
+    pub fn new(generator: Gjp2Generator, throttle: T) -> Self {
+        Self {
+            generator,
+            throttle,
+        }
+    }
+
+    /// Logs into an account, upgrading its stored hash along the way if the
+    /// generator's cost changed. Account state is only revealed once the
+    /// credentials are verified.
+    pub fn login(
+        &mut self,
+        store: &mut impl AccountStore,
+        request: &LoginRequest,
+        now: Timestamp
+    ) -> Result<LoginResponse, LoginError> {
+        let name = Name::parse(request.user_name).ok();
+        // Throttled by the name the account is found under, otherwise every
+        // stripped junk character would count as a different account
+        let throttle_key = name
+            .as_ref()
+            .map_or_else(|| request.user_name.to_owned(), Name::canonical);
+
+        self.throttle
+            .check(&throttle_key, request.ip, now)
+            .map_err(LoginError::Throttled)?;
+
+        let gjp2 = request.credential.to_client_gjp2();
+        let account = name.and_then(|name| store.find_by_name(&name));
+
+        let stored = account.map(|account| &account.gjp2);
+        let (verification, rehashed) = self
+            .generator
+            .verify_login(stored, &gjp2)?;
+
+        let account = match account {
+            Some(account) if verification.is_match() => account,
+            _ => {
+                self.throttle.record_failure(&throttle_key, request.ip, now);
+                return Err(LoginError::Failed);
+            }
+        };
+
+        self.throttle.record_success(&throttle_key);
+
+        // Leaderboard and creator bans don't keep players from logging in
+        if account.user.disabled {
+            return Err(LoginError::Disabled);
+        }
+
+        if !account.user.activation.is_active() {
+            return Err(LoginError::NotActivated);
+        }
+
+        if let (Some(linked), Some(steam_id)) =
+            (account.steam_id, request.steam_id)
+        {
+            if linked != steam_id {
+                return Err(LoginError::LinkedToDifferentSteamAccount);
+            }
+        }
+
+        let response = LoginResponse {
+            account_id: account.user.id,
+            user_id: account.user_id,
+        };
+
+        if let Some(rehashed) = rehashed {
+            store.update_gjp2(response.account_id, rehashed);
+        }
+
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account::{test_generator, test_register, MemoryAccountStore};
+    use crate::activation::ActivationPolicy;
+    use crate::throttle::{MemoryLoginThrottle, ThrottlePolicy};
+    use crate::user::Activation;
+    use std::net::Ipv4Addr;
+
+    fn request<'a>(user_name: &'a str, password: &str) -> LoginRequest<'a> {
+        let password = Password::parse(password).unwrap();
+        LoginRequest {
+            user_name,
+            credential: LoginCredential::Gjp2(
+                ClientGjp2::from_password(&password)
+            ),
+            steam_id: None,
+            ip: IpAddr::V4(Ipv4Addr::LOCALHOST),
+        }
+    }
+
+    #[test]
+    fn test_login() {
+        let mut store = MemoryAccountStore::new();
+        let throttle = MemoryLoginThrottle::new(ThrottlePolicy {
+            free_attempts: 3,
+            ..ThrottlePolicy::default()
+        });
+        let mut authenticator = Authenticator::new(test_generator(5), throttle);
+        let now = Timestamp::from_unix(1);
+        let mut login = |store: &mut MemoryAccountStore, request| {
+            authenticator.login(store, &request, now).map_err(|e| e.code())
+        };
+        let expected = Ok(LoginResponse { account_id: 1, user_id: 1 });
+        let (right, wrong) = ("_deez-nuts", "deez-nuts_");
+
+        let activation = ActivationPolicy::AutoActivate;
+        test_register(&mut store, "babygronk", "a@b.cd", activation);
+
+        assert_eq!(login(&mut store, request("skibidi", right)), Err(-1));
+        assert_eq!(login(&mut store, request("babygronk", wrong)), Err(-1));
+        assert_eq!(login(&mut store, request("BABYGRONK", right)), expected);
+        assert_eq!(store.accounts()[0].gjp2.cost(), Some(5));
+
+        let legacy = LoginRequest {
+            credential: LoginCredential::Password(
+                Password::parse(right).unwrap()
+            ),
+            ..request("babygronk", right)
+        };
+        assert_eq!(login(&mut store, legacy), expected);
+
+        store.accounts_mut()[0].steam_id = Some(7);
+        let steam = LoginRequest {
+            steam_id: Some(8),
+            ..request("babygronk", right)
+        };
+        assert_eq!(login(&mut store, steam), Err(-13));
+
+        store.accounts_mut()[0].user.activation = Activation::Pending;
+        assert_eq!(login(&mut store, request("babygronk", right)), Err(-11));
+        assert_eq!(login(&mut store, request("babygronk", wrong)), Err(-1));
+
+        store.accounts_mut()[0].user.disabled = true;
+        assert_eq!(login(&mut store, request("babygronk", right)), Err(-12));
+    }
+
+    #[test]
+    fn test_login_throttling() {
+        let mut store = MemoryAccountStore::new();
+        let throttle = MemoryLoginThrottle::new(ThrottlePolicy {
+            free_attempts: 1,
+            ..ThrottlePolicy::default()
+        });
+        let mut authenticator = Authenticator::new(test_generator(4), throttle);
+        let now = Timestamp::from_unix(1);
+        let mut login = |store: &mut MemoryAccountStore, request, now| {
+            authenticator.login(store, &request, now)
+        };
+
+        let activation = ActivationPolicy::AutoActivate;
+        test_register(&mut store, "babygronk", "a@b.cd", activation);
+
+        let right = || request("babygronk", "_deez-nuts");
+        let wrong = || request("babygronk", "deez-nuts_");
+        assert!(login(&mut store, right(), now).is_ok());
+
+        let failed = login(&mut store, wrong(), now);
+        assert!(matches!(failed, Err(LoginError::Failed)));
+        assert!(login(&mut store, right(), now).is_ok());
+
+        // The success only cleared the account's failures, not the IP's
+        let failed = login(&mut store, wrong(), now);
+        assert!(matches!(failed, Err(LoginError::Failed)));
+
+        let error = login(&mut store, right(), now).unwrap_err();
+        assert!(matches!(error, LoginError::Throttled(_)));
+        assert_eq!(error.code(), -12);
+
+        let lockout = ThrottlePolicy::default().base_lockout;
+        assert!(login(&mut store, right(), now + lockout).is_ok());
+    }
+
+    #[test]
+    fn test_login_throttling_junk_names() {
+        let mut store = MemoryAccountStore::new();
+        let throttle = MemoryLoginThrottle::new(ThrottlePolicy {
+            free_attempts: 1,
+            ..ThrottlePolicy::default()
+        });
+        let mut authenticator = Authenticator::new(test_generator(4), throttle);
+        let now = Timestamp::from_unix(1);
+
+        let activation = ActivationPolicy::AutoActivate;
+        test_register(&mut store, "babygronk", "a@b.cd", activation);
+
+        for (i, user_name) in ["babygronk", "babygronk_", "BabyGronk~~"]
+            .into_iter()
+            .enumerate()
+        {
+            let request = LoginRequest {
+                ip: IpAddr::V4(Ipv4Addr::new(127, 0, 0, i as u8 + 1)),
+                ..request(user_name, "deez-nuts_")
+            };
+            let result = authenticator.login(&mut store, &request, now);
+
+            match i {
+                2 => assert!(matches!(result, Err(LoginError::Throttled(_)))),
+                _ => assert!(matches!(result, Err(LoginError::Failed))),
+            }
+        }
+    }
+}
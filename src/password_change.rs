@@ -0,0 +1,318 @@
+// Password changes by the account owner and token-based resets issued by
+// server admins
+
+use crate::account::AccountStore;
+use crate::gjp2::{ClientGjp2, Gjp2, Gjp2Error, Gjp2Generator, Gjp2Verification};
+use crate::session::SessionRevocation;
+use crate::token;
+use crate::user::{Name, Password, PasswordError, PasswordPolicy};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// A successful password change, the new hash has to be persisted and the
+/// revocation applied to every session store.
+pub struct PasswordChange {
+    pub gjp2: Gjp2,
+    pub revocation: SessionRevocation,
+}
+
+#[derive(Debug)]
+pub enum PasswordChangeError {
+    WrongPassword,
+    MalformedHash,
+    InvalidPassword(PasswordError),
+    InvalidToken,
+    Gjp2(Gjp2Error),
+}
+
+impl fmt::Display for PasswordChangeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::WrongPassword => write!(f, "current password is wrong"),
+            Self::MalformedHash => write!(f, "stored gjp2 is malformed"),
+            Self::InvalidPassword(_) => write!(f, "new password is invalid"),
+            Self::InvalidToken => {
+                write!(f, "reset token is invalid or expired")
+            }
+            Self::Gjp2(_) => write!(f, "failed to hash new password"),
+        }
+    }
+}
+
+impl Error for PasswordChangeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::InvalidPassword(error) => Some(error),
+            Self::Gjp2(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<PasswordError> for PasswordChangeError {
+    fn from(error: PasswordError) -> Self {
+        Self::InvalidPassword(error)
+    }
+}
+
+impl From<Gjp2Error> for PasswordChangeError {
+    fn from(error: Gjp2Error) -> Self {
+        Self::Gjp2(error)
+    }
+}
+
+fn parse_password(
+    new_password: &str,
+    policy: Option<&PasswordPolicy>,
+    name: &Name
+) -> Result<Password, PasswordChangeError> {
+    let new_password = Password::parse(new_password)?;
+
+    if let Some(policy) = policy {
+        policy.check(&new_password, name)?;
+    }
+
+    Ok(new_password)
+}
+
+fn set_password(
+    generator: &mut Gjp2Generator,
+    account_id: u64,
+    password: Password
+) -> Result<PasswordChange, PasswordChangeError> {
+    Ok(PasswordChange {
+        gjp2: generator.generate_gjp2(password)?,
+        revocation: SessionRevocation { account_id },
+    })
+}
+
+/// Changes a password after verifying the current one, which can come from
+/// the plaintext through [`ClientGjp2::from_password`] or straight from the
+/// client. The policy should be the one registrations are held to.
+pub fn change_password(
+    generator: &mut Gjp2Generator,
+    account_id: u64,
+    name: &Name,
+    stored: &Gjp2,
+    current: &ClientGjp2,
+    new_password: &str,
+    policy: Option<&PasswordPolicy>
+) -> Result<PasswordChange, PasswordChangeError> {
+    match stored.verify_client(current) {
+        Gjp2Verification::Match => {}
+        Gjp2Verification::Mismatch => {
+            return Err(PasswordChangeError::WrongPassword);
+        }
+        Gjp2Verification::MalformedHash => {
+            return Err(PasswordChangeError::MalformedHash);
+        }
+    }
+
+    let new_password = parse_password(new_password, policy, name)?;
+    set_password(generator, account_id, new_password)
+}
+
+struct PendingReset {
+    account_id: u64,
+    expires_at: Instant,
+}
+
+/// Single-use password reset tokens, e.g. for admin-initiated resets.
+pub struct ResetTokens {
+    ttl: Duration,
+    pending: HashMap<String, PendingReset>,
+}
+
+impl ResetTokens {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            pending: HashMap::new(),
+        }
+    }
+
+    /// Issues a token for an account, invalidating any it had before.
+    pub fn issue(
+        &mut self,
+        account_id: u64,
+        now: Instant
+    ) -> Result<String, getrandom::Error> {
+        let token = token::random_token()?;
+
+        self.pending.retain(|_, reset| reset.account_id != account_id);
+        self.pending.insert(
+            token.clone(),
+            PendingReset {
+                account_id,
+                expires_at: now + self.ttl,
+            }
+        );
+
+        Ok(token)
+    }
+
+    /// Sets a new password for the token's account. The token stays usable
+    /// if the new password is rejected, so the form can be resubmitted.
+    pub fn redeem(
+        &mut self,
+        generator: &mut Gjp2Generator,
+        store: &impl AccountStore,
+        token: &str,
+        new_password: &str,
+        policy: Option<&PasswordPolicy>,
+        now: Instant
+    ) -> Result<PasswordChange, PasswordChangeError> {
+        let account_id = match self.pending.get(token) {
+            Some(reset) if now < reset.expires_at => reset.account_id,
+            Some(_) => {
+                self.pending.remove(token);
+                return Err(PasswordChangeError::InvalidToken);
+            }
+            None => return Err(PasswordChangeError::InvalidToken),
+        };
+
+        let account = store
+            .find_by_id(account_id)
+            .ok_or(PasswordChangeError::InvalidToken)?;
+        let new_password =
+            parse_password(new_password, policy, &account.user.name)?;
+
+        self.pending.remove(token);
+        set_password(generator, account_id, new_password)
+    }
+
+    /// Forgets tokens that expired without being redeemed.
+    pub fn purge_expired(&mut self, now: Instant) {
+        self.pending.retain(|_, reset| now < reset.expires_at);
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account::{test_generator, test_register, MemoryAccountStore};
+    use crate::activation::ActivationPolicy;
+
+    #[test]
+    fn test_password_change() -> Result<(), PasswordChangeError> {
+        let mut generator = test_generator(4);
+        let name = Name::parse("babygronk").unwrap();
+        let current = Password::parse("_deez-nuts")?;
+        let stored = generator.generate_gjp2(Password::parse("_deez-nuts")?)?;
+        let wrong = ClientGjp2::from_password(&Password::parse("deez-nuts_")?);
+        let current = ClientGjp2::from_password(&current);
+        let policy = PasswordPolicy::default();
+        let mut change = |current, new_password, policy| {
+            change_password(
+                &mut generator,
+                1,
+                &name,
+                &stored,
+                current,
+                new_password,
+                policy
+            )
+        };
+
+        assert!(matches!(
+            change(&wrong, "skibidi", None),
+            Err(PasswordChangeError::WrongPassword)
+        ));
+        assert!(matches!(
+            change(&current, "?!", None),
+            Err(PasswordChangeError::InvalidPassword(PasswordError::Empty))
+        ));
+        assert!(matches!(
+            change(&current, "password", Some(&policy)),
+            Err(PasswordChangeError::InvalidPassword(PasswordError::Common))
+        ));
+        assert!(matches!(
+            change(&current, "BabyGronk", Some(&policy)),
+            Err(PasswordChangeError::InvalidPassword(
+                PasswordError::MatchesName
+            ))
+        ));
+
+        let change = change(&current, "babygronk", None)?;
+        assert!(change.gjp2.verify(&Password::parse("babygronk")?).is_match());
+        assert_eq!(change.revocation, SessionRevocation { account_id: 1 });
+        Ok(())
+    }
+
+    #[test]
+    fn test_password_reset() -> Result<(), PasswordChangeError> {
+        let mut store = MemoryAccountStore::new();
+        let activation = ActivationPolicy::AutoActivate;
+        test_register(&mut store, "babygronk", "a@b.cd", activation);
+        test_register(&mut store, "skibidi", "b@c.de", activation);
+
+        let mut generator = test_generator(4);
+        let ttl = Duration::from_secs(60);
+        let now = Instant::now();
+        let policy = PasswordPolicy::default();
+        let mut tokens = ResetTokens::new(ttl);
+        let old = tokens.issue(1, now).unwrap();
+        let token = tokens.issue(1, now).unwrap();
+        let mut redeem = |tokens: &mut ResetTokens, token, password, now| {
+            tokens.redeem(
+                &mut generator,
+                &store,
+                token,
+                password,
+                Some(&policy),
+                now
+            )
+        };
+
+        assert_ne!(old, token);
+        assert!(matches!(
+            redeem(&mut tokens, &old, "skibidi", now),
+            Err(PasswordChangeError::InvalidToken)
+        ));
+        assert!(matches!(
+            redeem(&mut tokens, &token, "?!", now),
+            Err(PasswordChangeError::InvalidPassword(_))
+        ));
+        assert!(matches!(
+            redeem(&mut tokens, &token, "password", now),
+            Err(PasswordChangeError::InvalidPassword(PasswordError::Common))
+        ));
+        assert!(matches!(
+            redeem(&mut tokens, &token, "babygronk", now),
+            Err(PasswordChangeError::InvalidPassword(
+                PasswordError::MatchesName
+            ))
+        ));
+
+        let change = redeem(&mut tokens, &token, "skibidi", now)?;
+        assert!(change.gjp2.verify(&Password::parse("skibidi")?).is_match());
+        assert_eq!(change.revocation.account_id, 1);
+        assert!(matches!(
+            redeem(&mut tokens, &token, "skibidi", now),
+            Err(PasswordChangeError::InvalidToken)
+        ));
+
+        let token = tokens.issue(2, now).unwrap();
+        assert!(matches!(
+            redeem(&mut tokens, &token, "babygronk", now + ttl),
+            Err(PasswordChangeError::InvalidToken)
+        ));
+
+        tokens.issue(3, now).unwrap();
+        tokens.purge_expired(now);
+        assert_eq!(tokens.len(), 1);
+        tokens.purge_expired(now + ttl);
+        assert!(tokens.is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,266 @@
+// Account registration, mirroring the responses of
+// `accounts/registerGJAccount.php`
+
+use crate::account::{Account, AccountStore};
+use crate::activation::ActivationPolicy;
+use crate::gjp2::{Gjp2Error, Gjp2Generator};
+use crate::timestamp::Timestamp;
+use crate::user::{
+    Activation, Email, EmailError, Name, NameError, NamePolicy, Password,
+    PasswordError, PasswordPolicy, SocialMediaHandles, User,
+};
+use std::error::Error;
+use std::fmt;
+
+pub struct RegisterRequest<'a> {
+    pub user_name: &'a str,
+    pub password: &'a str,
+    /// Only sent by our web form, the game checks it client-side
+    pub password_confirmation: Option<&'a str>,
+    pub email: &'a str,
+}
+
+#[derive(Debug)]
+pub enum RegisterError {
+    NameTaken,
+    EmailTaken,
+    InvalidName(NameError),
+    InvalidPassword(PasswordError),
+    InvalidEmail(EmailError),
+    PasswordsDontMatch,
+    Gjp2(Gjp2Error),
+}
+
+impl RegisterError {
+    /// The response code the client shows the matching dialog for.
+    pub fn code(&self) -> i32 {
+        match self {
+            Self::Gjp2(_) => -1,
+            Self::NameTaken | Self::InvalidName(NameError::Reserved) => -2,
+            Self::EmailTaken => -3,
+            Self::InvalidName(NameError::TooShort) => -9,
+            Self::InvalidName(_) => -4,
+            Self::InvalidPassword(PasswordError::TooShort) => -8,
+            Self::InvalidPassword(_) => -5,
+            Self::InvalidEmail(_) => -6,
+            Self::PasswordsDontMatch => -7,
+        }
+    }
+}
+
+impl fmt::Display for RegisterError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::NameTaken => write!(f, "name is already taken"),
+            Self::EmailTaken => write!(f, "email is already taken"),
+            Self::InvalidName(_) => write!(f, "name is invalid"),
+            Self::InvalidPassword(_) => write!(f, "password is invalid"),
+            Self::InvalidEmail(_) => write!(f, "email is invalid"),
+            Self::PasswordsDontMatch => write!(f, "passwords don't match"),
+            Self::Gjp2(_) => write!(f, "failed to hash password"),
+        }
+    }
+}
+
+impl Error for RegisterError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::InvalidName(error) => Some(error),
+            Self::InvalidPassword(error) => Some(error),
+            Self::InvalidEmail(error) => Some(error),
+            Self::Gjp2(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<NameError> for RegisterError {
+    fn from(error: NameError) -> Self {
+        Self::InvalidName(error)
+    }
+}
+
+impl From<PasswordError> for RegisterError {
+    fn from(error: PasswordError) -> Self {
+        Self::InvalidPassword(error)
+    }
+}
+
+impl From<EmailError> for RegisterError {
+    fn from(error: EmailError) -> Self {
+        Self::InvalidEmail(error)
+    }
+}
+
+impl From<Gjp2Error> for RegisterError {
+    fn from(error: Gjp2Error) -> Self {
+        Self::Gjp2(error)
+    }
+}
+
+pub struct Registrar {
+    generator: Gjp2Generator,
+    activation: ActivationPolicy,
+    names: NamePolicy,
+    passwords: Option<PasswordPolicy>,
+}
+
+impl Registrar {
+    pub fn new(
+        generator: Gjp2Generator,
+        activation: ActivationPolicy
+    ) -> Self {
+        Self {
+            generator,
+            activation,
+            names: NamePolicy::default(),
+            passwords: None,
+        }
+    }
+
+    pub fn set_name_policy(&mut self, names: NamePolicy) -> &mut Self {
+        self.names = names;
+        self
+    }
+
+    pub fn set_password_policy(
+        &mut self,
+        passwords: PasswordPolicy
+    ) -> &mut Self {
+        self.passwords = Some(passwords);
+        self
+    }
+
+    /// Registers an account and returns its ID. Under
+    /// [`ActivationPolicy::EmailVerification`] the account stays pending
+    /// until its owner redeems the token sent by an
+    /// [`Activator`](crate::activation::Activator).
+    pub fn register(
+        &mut self,
+        store: &mut impl AccountStore,
+        request: &RegisterRequest
+    ) -> Result<u64, RegisterError> {
+        let name = Name::parse(request.user_name)?;
+        self.names.check(&name)?;
+
+        let password = Password::parse(request.password)?;
+
+        if let Some(passwords) = &self.passwords {
+            passwords.check(&password, &name)?;
+        }
+
+        if let Some(confirmation) = request.password_confirmation {
+            if Password::parse(confirmation).as_ref() != Ok(&password) {
+                return Err(RegisterError::PasswordsDontMatch);
+            }
+        }
+
+        let email = Email::parse(request.email)?;
+
+        let confusable = self.names.detects_confusables()
+            && store.confusable_name_taken(&name);
+
+        if confusable || store.name_taken(&name) {
+            return Err(RegisterError::NameTaken);
+        }
+
+        if store.email_taken(&email) {
+            return Err(RegisterError::EmailTaken);
+        }
+
+        let gjp2 = self.generator.generate_gjp2(password)?;
+        let mut user = User::new(
+            store.next_account_id(),
+            name,
+            email,
+            SocialMediaHandles::new("", "", ""),
+            Timestamp::now()
+        );
+        let id = user.id;
+
+        if self.activation == ActivationPolicy::AutoActivate {
+            user.activation = Activation::Active;
+        }
+        let user_id = store.next_user_id();
+
+        store.insert(Account {
+            user,
+            user_id,
+            gjp2,
+            steam_id: None,
+        });
+        Ok(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account::{test_generator, MemoryAccountStore};
+
+    fn request<'a>(
+        user_name: &'a str,
+        password: &'a str,
+        email: &'a str
+    ) -> RegisterRequest<'a> {
+        RegisterRequest {
+            user_name,
+            password,
+            password_confirmation: None,
+            email,
+        }
+    }
+
+    #[test]
+    fn test_registration() {
+        let generator = test_generator(4);
+        let activation = ActivationPolicy::EmailVerification;
+        let mut registrar = Registrar::new(generator, activation);
+        let mut store = MemoryAccountStore::new();
+        let pass = "_deez-nuts";
+        let mut register = |request: RegisterRequest| {
+            registrar.register(&mut store, &request).map_err(|e| e.code())
+        };
+
+        assert_eq!(register(request("?!", pass, "a@b.cd")), Err(-4));
+        assert_eq!(register(request("ab", pass, "a@b.cd")), Err(-9));
+        assert_eq!(register(request("babygronk", "?!", "a@b.cd")), Err(-5));
+        assert_eq!(register(request("babygronk", "deez", "a@b.cd")), Err(-8));
+        assert_eq!(register(request("babygronk", pass, "a@")), Err(-6));
+        assert_eq!(
+            register(RegisterRequest {
+                password_confirmation: Some("deez-nuts_"),
+                ..request("babygronk", pass, "a@b.cd")
+            }),
+            Err(-7)
+        );
+
+        assert_eq!(register(request("babygronk", pass, "a@b.cd")), Ok(1));
+        assert_eq!(register(request("BabyGronk", pass, "b@b.cd")), Err(-2));
+        assert_eq!(register(request("skibidi", pass, "A@B.cd")), Err(-3));
+        assert_eq!(register(request("Moderator", pass, "b@b.cd")), Err(-2));
+        assert_eq!(register(request("babygr0nk", pass, "b@b.cd")), Ok(2));
+
+        let account = &store.accounts()[0];
+        assert_eq!(account.user.name.as_str(), "babygronk");
+        assert_eq!(account.user.activation, Activation::Pending);
+        let password = Password::parse(pass).unwrap();
+        assert!(account.gjp2.verify(&password).is_match());
+
+        registrar.set_name_policy(NamePolicy::new(&["robtop"], true));
+        let mut register = |request: RegisterRequest| {
+            registrar.register(&mut store, &request).map_err(|e| e.code())
+        };
+        assert_eq!(register(request("R0bT0p", pass, "c@b.cd")), Err(-2));
+        assert_eq!(register(request("BabyGr0nk", pass, "c@b.cd")), Err(-2));
+        assert_eq!(register(request("moderator", pass, "c@b.cd")), Ok(3));
+
+        registrar.set_password_policy(PasswordPolicy::default());
+        let mut register = |request: RegisterRequest| {
+            registrar.register(&mut store, &request).map_err(|e| e.code())
+        };
+        assert_eq!(register(request("skibidi", "qwerty", "d@b.cd")), Err(-5));
+        assert_eq!(register(request("skibidi", "skibidi", "d@b.cd")), Err(-5));
+        assert_eq!(register(request("skibidi", pass, "d@b.cd")), Ok(4));
+    }
+}
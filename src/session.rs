@@ -0,0 +1,185 @@
+// Caches successful gjp2 verifications so authenticated requests after the
+// first one don't each pay for a bcrypt verification
+
+use crate::gjp2::{ClientGjp2, Gjp2, Gjp2Verification};
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+use subtle::ConstantTimeEq;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+type Fingerprint = [u8; 20];
+
+const KEY_LEN: usize = 32;
+
+/// Emitted when an account's credentials change, sessions opened with the
+/// old ones must not outlive it.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct SessionRevocation {
+    pub account_id: u64,
+}
+
+struct Session {
+    fingerprint: Fingerprint,
+    expires_at: Instant,
+}
+
+pub struct SessionStore {
+    key: [u8; KEY_LEN],
+    ttl: Duration,
+    sessions: HashMap<u64, Session>,
+}
+
+impl SessionStore {
+    /// Creates a store keyed with a random secret, so fingerprints are
+    /// useless outside of this process.
+    pub fn new(ttl: Duration) -> Result<Self, getrandom::Error> {
+        let mut key = [0; KEY_LEN];
+        getrandom::fill(&mut key)?;
+        Ok(Self::with_key(key, ttl))
+    }
+
+    pub fn with_key(key: [u8; KEY_LEN], ttl: Duration) -> Self {
+        Self {
+            key,
+            ttl,
+            sessions: HashMap::new(),
+        }
+    }
+
+    fn fingerprint(&self, account_id: u64, gjp2: &ClientGjp2) -> Fingerprint {
+        let mut mac = Hmac::<Sha1>::new_from_slice(&self.key)
+            .expect("HMAC accepts keys of any length");
+        mac.update(&account_id.to_le_bytes());
+        mac.update(gjp2.as_bytes());
+        mac.finalize().into_bytes().into()
+    }
+
+    /// Starts a session, only call this after `gjp2` was verified against
+    /// the account's stored [`Gjp2`].
+    pub fn establish(
+        &mut self,
+        account_id: u64,
+        gjp2: &ClientGjp2,
+        now: Instant
+    ) {
+        let session = Session {
+            fingerprint: self.fingerprint(account_id, gjp2),
+            expires_at: now + self.ttl,
+        };
+
+        self.sessions.insert(account_id, session);
+    }
+
+    pub fn validate(
+        &self,
+        account_id: u64,
+        gjp2: &ClientGjp2,
+        now: Instant
+    ) -> bool {
+        let Some(session) = self.sessions.get(&account_id) else {
+            return false;
+        };
+
+        let fingerprint = self.fingerprint(account_id, gjp2);
+        let matches: bool = session.fingerprint.ct_eq(&fingerprint).into();
+        matches && now < session.expires_at
+    }
+
+    /// Verifies a request against an open session and falls back to bcrypt,
+    /// opening a session if that succeeds.
+    pub fn verify(
+        &mut self,
+        account_id: u64,
+        stored: &Gjp2,
+        gjp2: &ClientGjp2,
+        now: Instant
+    ) -> Gjp2Verification {
+        if self.validate(account_id, gjp2, now) {
+            return Gjp2Verification::Match;
+        }
+
+        let verification = stored.verify_client(gjp2);
+
+        if verification.is_match() {
+            self.establish(account_id, gjp2, now);
+        }
+
+        verification
+    }
+
+    /// Ends an account's session, e.g. when its password changes.
+    pub fn invalidate(&mut self, account_id: u64) {
+        self.sessions.remove(&account_id);
+    }
+
+    pub fn revoke(&mut self, revocation: SessionRevocation) {
+        self.invalidate(revocation.account_id);
+    }
+
+    pub fn purge_expired(&mut self, now: Instant) {
+        self.sessions.retain(|_, session| now < session.expires_at);
+    }
+
+    pub fn len(&self) -> usize {
+        self.sessions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sessions.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account::test_generator;
+    use crate::user::Password;
+
+    fn client_gjp2(password: &str) -> ClientGjp2 {
+        ClientGjp2::from_password(&Password::parse(password).unwrap())
+    }
+
+    #[test]
+    fn test_sessions() {
+        let gjp2 = client_gjp2("_deez-nuts");
+        let other = client_gjp2("deez-nuts_");
+        let ttl = Duration::from_secs(60);
+        let now = Instant::now();
+        let mut sessions = SessionStore::new(ttl).unwrap();
+
+        sessions.establish(1, &gjp2, now);
+        assert!(sessions.validate(1, &gjp2, now));
+        assert!(!sessions.validate(1, &other, now));
+        assert!(!sessions.validate(2, &gjp2, now));
+        assert!(!sessions.validate(1, &gjp2, now + ttl));
+
+        sessions.purge_expired(now + ttl);
+        assert!(sessions.is_empty());
+
+        sessions.establish(1, &gjp2, now);
+        sessions.invalidate(1);
+        assert!(!sessions.validate(1, &gjp2, now));
+    }
+
+    #[test]
+    fn test_session_verification() {
+        let gjp2 = client_gjp2("_deez-nuts");
+        let stored = test_generator(4)
+            .generate_gjp2_from_client(&gjp2)
+            .unwrap();
+        let now = Instant::now();
+        let mut sessions = SessionStore::new(Duration::from_secs(60)).unwrap();
+
+        assert_eq!(
+            sessions.verify(1, &Gjp2::new(""), &gjp2, now),
+            Gjp2Verification::MalformedHash
+        );
+        assert!(sessions.is_empty());
+        assert!(sessions.verify(1, &stored, &gjp2, now).is_match());
+        assert!(sessions.validate(1, &gjp2, now));
+
+        // Served from the session without touching the stored hash
+        assert!(sessions.verify(1, &Gjp2::new(""), &gjp2, now).is_match());
+    }
+}
@@ -0,0 +1,115 @@
+// Account settings, mirroring the responses of
+// `updateGJAccSettings20.php`
+
+use crate::user::{
+    AllowFriendRequestsFrom, AllowMessagesFrom, DisplayCommentHistoryTo,
+    PrivacySettings, SocialMediaHandles, User,
+};
+use std::error::Error;
+use std::fmt;
+
+/// The form fields of the settings page, the client always sends all of
+/// them so empty handles clear the stored ones.
+pub struct SettingsRequest<'a> {
+    /// `mS`
+    pub messages: &'a str,
+    /// `frS`
+    pub friend_requests: &'a str,
+    /// `cS`
+    pub comment_history: &'a str,
+    /// `yt`
+    pub youtube: &'a str,
+    pub twitter: &'a str,
+    pub twitch: &'a str,
+}
+
+#[derive(PartialEq, Debug)]
+pub enum SettingsError {
+    /// Holds the form field with the unknown value
+    InvalidValue(&'static str),
+}
+
+impl SettingsError {
+    /// Always -1, the only failure response `updateGJAccSettings20.php` has.
+    pub fn code(&self) -> i32 {
+        match self {
+            Self::InvalidValue(_) => -1,
+        }
+    }
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::InvalidValue(field) => write!(f, "invalid value for {field}"),
+        }
+    }
+}
+
+impl Error for SettingsError {}
+
+/// Applies the settings to an already authenticated user, nothing is changed
+/// unless every value is valid.
+pub fn update_settings(
+    user: &mut User,
+    request: &SettingsRequest
+) -> Result<(), SettingsError> {
+    let privacy = PrivacySettings {
+        messages: AllowMessagesFrom::parse(request.messages)
+            .ok_or(SettingsError::InvalidValue("mS"))?,
+        friend_requests: AllowFriendRequestsFrom::parse(request.friend_requests)
+            .ok_or(SettingsError::InvalidValue("frS"))?,
+        comment_history: DisplayCommentHistoryTo::parse(request.comment_history)
+            .ok_or(SettingsError::InvalidValue("cS"))?,
+    };
+
+    user.privacy = privacy;
+    user.social_media_handles = SocialMediaHandles::new(
+        request.youtube,
+        request.twitter,
+        request.twitch,
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::user::test_user;
+
+    #[test]
+    fn test_update_settings() -> Result<(), SettingsError> {
+        let mut user = test_user(1);
+        assert_eq!(user.privacy, PrivacySettings::default());
+
+        update_settings(&mut user, &SettingsRequest {
+            messages: "1",
+            friend_requests: "1",
+            comment_history: "2",
+            youtube: "~xd",
+            twitter: "",
+            twitch: "xd",
+        })?;
+        assert_eq!(user.privacy.messages, AllowMessagesFrom::Friends);
+        assert_eq!(user.privacy.friend_requests, AllowFriendRequestsFrom::None);
+        assert_eq!(user.privacy.comment_history, DisplayCommentHistoryTo::None);
+        assert_eq!(user.social_media_handles.youtube(), Some("xd".to_string()));
+        assert_eq!(user.social_media_handles.twitch(), Some("xd".to_string()));
+
+        let invalid = SettingsRequest {
+            messages: "0",
+            friend_requests: "2",
+            comment_history: "0",
+            youtube: "",
+            twitter: "",
+            twitch: "",
+        };
+        assert_eq!(
+            update_settings(&mut user, &invalid),
+            Err(SettingsError::InvalidValue("frS"))
+        );
+        assert_eq!(user.privacy.messages, AllowMessagesFrom::Friends);
+        assert_eq!(user.social_media_handles.youtube(), Some("xd".to_string()));
+        Ok(())
+    }
+}
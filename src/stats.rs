@@ -0,0 +1,527 @@
+// Player stat submissions, mirroring `updateGJUserScore22.php`. Submitted
+// values are checked against what can actually be earned on the server and
+// suspiciously large jumps are held back for a moderator
+
+use crate::account::AccountStore;
+use crate::timestamp::Timestamp;
+use crate::user::{Stat, Stats, User};
+use std::error::Error;
+use std::fmt;
+use std::time::Duration;
+
+/// The stats the client reports, the rest are calculated by the server.
+pub const SUBMITTED_STATS: [Stat; 6] = [
+    Stat::Stars,
+    Stat::Moons,
+    Stat::Diamonds,
+    Stat::Coins,
+    Stat::UserCoins,
+    Stat::Demons,
+];
+
+#[derive(Debug, Clone, Copy)]
+pub struct StatLimits {
+    /// Most of each stat that can be earned, e.g. the stars of every rated
+    /// level or the diamonds of every chest and daily level so far
+    pub maximum: Stats,
+    /// Largest increase accepted within [`StatLimits::window`] without a
+    /// review, however many updates it is spread over
+    pub max_increase: Stats,
+    pub window: Duration,
+}
+
+/// One applied update, kept so moderators can see how a player's stats got
+/// where they are.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct StatsDelta {
+    pub account_id: u64,
+    pub at: Timestamp,
+    pub before: Stats,
+    pub after: Stats,
+}
+
+impl StatsDelta {
+    pub fn change(&self, stat: Stat) -> i64 {
+        i64::from(self.after.get(stat)) - i64::from(self.before.get(stat))
+    }
+}
+
+/// An update held back until a moderator approves or rejects it.
+#[derive(PartialEq, Debug, Clone)]
+pub struct StatsReview {
+    pub id: u64,
+    pub account_id: u64,
+    pub at: Timestamp,
+    pub submitted: Stats,
+    /// Stats that increased by more than [`StatLimits::max_increase`] within
+    /// the window
+    pub jumps: Vec<Stat>,
+}
+
+/// Lets the history and pending reviews live wherever the rest of the
+/// accounts do.
+pub trait StatsLog {
+    fn record(&mut self, delta: StatsDelta);
+    /// The account's deltas recorded at or after `since`, oldest first.
+    fn history_since(
+        &self,
+        account_id: u64,
+        since: Timestamp
+    ) -> Vec<StatsDelta>;
+    fn next_review_id(&mut self) -> u64;
+    fn flag(&mut self, review: StatsReview);
+    fn review(&self, review_id: u64) -> Option<StatsReview>;
+    /// ID of the account's pending review with the same submitted stats.
+    fn pending_review(
+        &self,
+        account_id: u64,
+        submitted: &Stats
+    ) -> Option<u64>;
+    fn take_review(&mut self, review_id: u64) -> Option<StatsReview>;
+    /// Drops the account's pending reviews flagged before `review_id`, once
+    /// newer stats have been applied they would only roll them back.
+    fn drop_reviews_before(&mut self, account_id: u64, review_id: u64);
+}
+
+#[derive(Default)]
+pub struct MemoryStatsLog {
+    history: Vec<StatsDelta>,
+    reviews: Vec<StatsReview>,
+    review_count: u64,
+}
+
+impl MemoryStatsLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn history(&self, account_id: u64) -> Vec<&StatsDelta> {
+        self.history
+            .iter()
+            .filter(|delta| delta.account_id == account_id)
+            .collect()
+    }
+
+    pub fn reviews(&self) -> &[StatsReview] {
+        &self.reviews
+    }
+}
+
+impl StatsLog for MemoryStatsLog {
+    fn record(&mut self, delta: StatsDelta) {
+        self.history.push(delta);
+    }
+
+    fn history_since(
+        &self,
+        account_id: u64,
+        since: Timestamp
+    ) -> Vec<StatsDelta> {
+        self.history
+            .iter()
+            .filter(|delta| delta.account_id == account_id)
+            .filter(|delta| since <= delta.at)
+            .copied()
+            .collect()
+    }
+
+    fn next_review_id(&mut self) -> u64 {
+        self.review_count += 1;
+        self.review_count
+    }
+
+    fn flag(&mut self, review: StatsReview) {
+        self.reviews.push(review);
+    }
+
+    fn review(&self, review_id: u64) -> Option<StatsReview> {
+        self.reviews
+            .iter()
+            .find(|review| review.id == review_id)
+            .cloned()
+    }
+
+    fn pending_review(
+        &self,
+        account_id: u64,
+        submitted: &Stats
+    ) -> Option<u64> {
+        self.reviews
+            .iter()
+            .find(|review| {
+                review.account_id == account_id
+                    && review.submitted == *submitted
+            })
+            .map(|review| review.id)
+    }
+
+    fn take_review(&mut self, review_id: u64) -> Option<StatsReview> {
+        let index = self
+            .reviews
+            .iter()
+            .position(|review| review.id == review_id)?;
+
+        Some(self.reviews.remove(index))
+    }
+
+    fn drop_reviews_before(&mut self, account_id: u64, review_id: u64) {
+        self.reviews.retain(|review| {
+            review.account_id != account_id || review.id >= review_id
+        });
+    }
+}
+
+#[derive(PartialEq, Debug)]
+pub enum StatsUpdate {
+    Applied,
+    /// Held back for review, the client is told the update went through
+    Flagged(u64),
+}
+
+#[derive(PartialEq, Debug)]
+pub enum StatsError {
+    ExceedsMaximum(Stat, u32),
+    UnknownReview,
+    UnknownAccount,
+}
+
+impl StatsError {
+    /// Always -1, the only failure response `updateGJUserScore22.php` has.
+    pub fn code(&self) -> i32 {
+        -1
+    }
+}
+
+impl fmt::Display for StatsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::ExceedsMaximum(stat, value) => {
+                write!(f, "{value} {stat:?} is more than can be earned")
+            }
+            Self::UnknownReview => write!(f, "review does not exist"),
+            Self::UnknownAccount => write!(f, "account does not exist"),
+        }
+    }
+}
+
+impl Error for StatsError {}
+
+fn merge(current: &Stats, submitted: &Stats) -> Stats {
+    let mut merged = *current;
+
+    for stat in SUBMITTED_STATS {
+        merged.set(stat, submitted.get(stat));
+    }
+
+    merged
+}
+
+pub struct StatsUpdater<L: StatsLog> {
+    limits: StatLimits,
+    log: L,
+}
+
+impl<L: StatsLog> StatsUpdater<L> {
+    pub fn new(limits: StatLimits, log: L) -> Self {
+        Self { limits, log }
+    }
+
+    /// The maxima grow with every rated level and daily chest, keep them up
+    /// to date or legitimate players will start getting rejected.
+    pub fn set_limits(&mut self, limits: StatLimits) -> &mut Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn log(&self) -> &L {
+        &self.log
+    }
+
+    fn apply(&mut self, user: &mut User, submitted: &Stats, now: Timestamp) {
+        let before = user.stats;
+        user.stats = merge(&before, submitted);
+
+        // The client resubmits its stats every time the menu is opened
+        if user.stats != before {
+            self.log.record(StatsDelta {
+                account_id: user.id,
+                at: now,
+                before,
+                after: user.stats,
+            });
+        }
+    }
+
+    /// The account's stats as of the start of the window, so increases
+    /// can't be split over several updates to stay under the limit.
+    fn baseline(&self, user: &User, now: Timestamp) -> Stats {
+        let window = self.limits.window.as_secs();
+        let since = Timestamp::from_unix(now.as_unix().saturating_sub(window));
+
+        self.log
+            .history_since(user.id, since)
+            .first()
+            .map_or(user.stats, |delta| delta.before)
+    }
+
+    /// Rejects values above the maxima outright and holds back increases
+    /// above [`StatLimits::max_increase`], decreases are always applied.
+    pub fn submit(
+        &mut self,
+        user: &mut User,
+        submitted: &Stats,
+        now: Timestamp
+    ) -> Result<StatsUpdate, StatsError> {
+        // Anything the client doesn't report is ignored
+        let submitted = merge(&Stats::default(), submitted);
+
+        for stat in SUBMITTED_STATS {
+            let value = submitted.get(stat);
+
+            if value > self.limits.maximum.get(stat) {
+                return Err(StatsError::ExceedsMaximum(stat, value));
+            }
+        }
+
+        let baseline = self.baseline(user, now);
+        let jumps: Vec<Stat> = SUBMITTED_STATS
+            .into_iter()
+            .filter(|&stat| {
+                let increase =
+                    submitted.get(stat).saturating_sub(baseline.get(stat));
+                increase > self.limits.max_increase.get(stat)
+            })
+            .collect();
+
+        if !jumps.is_empty() {
+            // The client resubmits the same stats until they are accepted
+            if let Some(id) = self.log.pending_review(user.id, &submitted) {
+                return Ok(StatsUpdate::Flagged(id));
+            }
+
+            let id = self.log.next_review_id();
+            self.log.flag(StatsReview {
+                id,
+                account_id: user.id,
+                at: now,
+                submitted,
+                jumps,
+            });
+            return Ok(StatsUpdate::Flagged(id));
+        }
+
+        // Every pending review is older than the stats applied now
+        self.log.drop_reviews_before(user.id, u64::MAX);
+        self.apply(user, &submitted, now);
+        Ok(StatsUpdate::Applied)
+    }
+
+    /// Applies a held back update on top of the account's current stats.
+    /// Reviews superseded by a later update no longer exist.
+    pub fn approve(
+        &mut self,
+        store: &mut impl AccountStore,
+        review_id: u64,
+        now: Timestamp
+    ) -> Result<(), StatsError> {
+        let review = self
+            .log
+            .review(review_id)
+            .ok_or(StatsError::UnknownReview)?;
+        let account = store
+            .find_by_id_mut(review.account_id)
+            .ok_or(StatsError::UnknownAccount)?;
+
+        self.log.take_review(review_id);
+        self.log.drop_reviews_before(review.account_id, review_id);
+        self.apply(&mut account.user, &review.submitted, now);
+        Ok(())
+    }
+
+    pub fn reject(
+        &mut self,
+        review_id: u64
+    ) -> Result<StatsReview, StatsError> {
+        self.log
+            .take_review(review_id)
+            .ok_or(StatsError::UnknownReview)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account::{test_account, MemoryAccountStore};
+    use crate::user::test_user;
+
+    fn limits() -> StatLimits {
+        StatLimits {
+            maximum: Stats {
+                stars: 1000,
+                moons: 1000,
+                coins: 100,
+                user_coins: 100,
+                diamonds: 1000,
+                demons: 10,
+                ..Stats::default()
+            },
+            max_increase: Stats {
+                stars: 100,
+                moons: 100,
+                coins: 10,
+                user_coins: 10,
+                diamonds: 100,
+                demons: 1,
+                ..Stats::default()
+            },
+            window: Duration::from_secs(60 * 60),
+        }
+    }
+
+    #[test]
+    fn test_stats_update() -> Result<(), StatsError> {
+        let mut updater = StatsUpdater::new(limits(), MemoryStatsLog::new());
+        let mut store = MemoryAccountStore::new();
+        store.insert(test_account(test_user(1)));
+        let user = &mut store.find_by_id_mut(1).unwrap().user;
+        let now = Timestamp::from_unix(1);
+        let mut stats = Stats {
+            stars: 100,
+            creator_points: 5,
+            ..Stats::default()
+        };
+
+        let update = updater.submit(user, &stats, now)?;
+        assert_eq!(update, StatsUpdate::Applied);
+        assert_eq!(user.stats.stars, 100);
+        assert_eq!(user.stats.creator_points, 0);
+
+        updater.submit(user, &stats, now)?;
+        assert_eq!(updater.log().history(1).len(), 1);
+        assert_eq!(updater.log().history(1)[0].change(Stat::Stars), 100);
+
+        stats.stars = 1001;
+        assert_eq!(
+            updater.submit(user, &stats, now),
+            Err(StatsError::ExceedsMaximum(Stat::Stars, 1001))
+        );
+
+        stats.stars = 500;
+        stats.demons = 2;
+        let update = updater.submit(user, &stats, now)?;
+        assert_eq!(update, StatsUpdate::Flagged(1));
+        assert_eq!(user.stats.stars, 100);
+        let jumps = &updater.log().reviews()[0].jumps;
+        assert_eq!(jumps, &[Stat::Stars, Stat::Demons]);
+
+        let update = updater.submit(user, &stats, now)?;
+        assert_eq!(update, StatsUpdate::Flagged(1));
+        assert_eq!(updater.log().reviews().len(), 1);
+
+        updater.approve(&mut store, 1, now)?;
+        let user = &mut store.find_by_id_mut(1).unwrap().user;
+        assert_eq!(user.stats.stars, 500);
+        assert!(updater.log().reviews().is_empty());
+        assert_eq!(updater.reject(1), Err(StatsError::UnknownReview));
+
+        stats.stars = 0;
+        stats.demons = 0;
+        updater.submit(user, &stats, now)?;
+        assert_eq!(user.stats.stars, 0);
+        assert_eq!(updater.log().history(1).len(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_stats_increase_window() -> Result<(), StatsError> {
+        let window = limits().window.as_secs();
+        let mut updater = StatsUpdater::new(limits(), MemoryStatsLog::new());
+        let mut user = test_user(1);
+        let now = Timestamp::from_unix(window);
+        let mut stats = Stats::default();
+
+        for stars in [60, 100] {
+            stats.stars = stars;
+            let update = updater.submit(&mut user, &stats, now)?;
+            assert_eq!(update, StatsUpdate::Applied);
+        }
+
+        stats.stars = 160;
+        let update = updater.submit(&mut user, &stats, now)?;
+        assert_eq!(update, StatsUpdate::Flagged(1));
+
+        let later = Timestamp::from_unix(2 * window + 1);
+        let update = updater.submit(&mut user, &stats, later)?;
+        assert_eq!(update, StatsUpdate::Applied);
+        assert_eq!(user.stats.stars, 160);
+        Ok(())
+    }
+
+    #[test]
+    fn test_approve_superseded() -> Result<(), StatsError> {
+        let window = limits().window.as_secs();
+        let mut updater = StatsUpdater::new(limits(), MemoryStatsLog::new());
+        let mut store = MemoryAccountStore::new();
+        store.insert(test_account(test_user(1)));
+        let user = &mut store.find_by_id_mut(1).unwrap().user;
+        let now = Timestamp::from_unix(window);
+        let mut stats = Stats {
+            stars: 100,
+            ..Stats::default()
+        };
+
+        assert_eq!(updater.submit(user, &stats, now)?, StatsUpdate::Applied);
+        stats.stars = 160;
+        assert_eq!(updater.submit(user, &stats, now)?, StatsUpdate::Flagged(1));
+
+        let later = Timestamp::from_unix(2 * window + 1);
+        assert_eq!(updater.submit(user, &stats, later)?, StatsUpdate::Applied);
+        stats.stars = 250;
+        let later = Timestamp::from_unix(3 * window + 2);
+        assert_eq!(updater.submit(user, &stats, later)?, StatsUpdate::Applied);
+
+        assert_eq!(
+            updater.approve(&mut store, 1, later),
+            Err(StatsError::UnknownReview)
+        );
+        let user = &mut store.find_by_id_mut(1).unwrap().user;
+        assert_eq!(user.stats.stars, 250);
+
+        for stars in [500, 600] {
+            stats.stars = stars;
+            updater.submit(user, &stats, later)?;
+        }
+        updater.approve(&mut store, 3, later)?;
+        assert_eq!(
+            updater.approve(&mut store, 2, later),
+            Err(StatsError::UnknownReview)
+        );
+        assert_eq!(store.accounts()[0].user.stats.stars, 600);
+        Ok(())
+    }
+
+    #[test]
+    fn test_approve_unknown_account() {
+        let mut updater = StatsUpdater::new(limits(), MemoryStatsLog::new());
+        let mut store = MemoryAccountStore::new();
+        let mut user = test_user(1);
+        let stats = Stats {
+            stars: 500,
+            ..Stats::default()
+        };
+        let now = Timestamp::from_unix(1);
+
+        assert_eq!(
+            updater.submit(&mut user, &stats, now),
+            Ok(StatsUpdate::Flagged(1))
+        );
+        assert_eq!(
+            updater.approve(&mut store, 1, now),
+            Err(StatsError::UnknownAccount)
+        );
+        assert_eq!(updater.log().reviews().len(), 1);
+
+        store.insert(test_account(user));
+        assert_eq!(updater.approve(&mut store, 1, now), Ok(()));
+    }
+}
@@ -0,0 +1,263 @@
+// Login throttling against credential stuffing, failures are tracked per
+// account name and per IP with exponentially growing lockouts
+
+use crate::timestamp::Timestamp;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::net::IpAddr;
+use std::time::Duration;
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct Lockout {
+    pub until: Timestamp,
+}
+
+/// Lets the failure counts live wherever the rest of the accounts do. Names
+/// are passed in their [canonical](crate::user::Name::canonical) form, or as
+/// given when they don't parse, and implementations must compare them
+/// case-insensitively.
+pub trait LoginThrottle {
+    fn check(
+        &self,
+        name: &str,
+        ip: IpAddr,
+        now: Timestamp
+    ) -> Result<(), Lockout>;
+    fn record_failure(&mut self, name: &str, ip: IpAddr, now: Timestamp);
+    /// Clears the account's failures, the IP's are kept so one valid account
+    /// can't be used to launder attempts on others.
+    fn record_success(&mut self, name: &str);
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ThrottlePolicy {
+    /// Failures allowed before the first lockout
+    pub free_attempts: u32,
+    /// Length of the first lockout, doubled by each failure after it
+    pub base_lockout: Duration,
+    pub max_lockout: Duration,
+    /// How long failures are remembered after the last one, or after the
+    /// lockout they led to ends
+    pub idle_timeout: Duration,
+}
+
+impl Default for ThrottlePolicy {
+    fn default() -> Self {
+        Self {
+            free_attempts: 5,
+            base_lockout: Duration::from_secs(30),
+            max_lockout: Duration::from_secs(60 * 60),
+            idle_timeout: Duration::from_secs(15 * 60),
+        }
+    }
+}
+
+impl ThrottlePolicy {
+    fn lockout(&self, failures: u32) -> Option<Duration> {
+        let excess = failures.checked_sub(self.free_attempts)?;
+
+        if excess == 0 {
+            return None;
+        }
+
+        let lockout = 2u32
+            .checked_pow(excess - 1)
+            .and_then(|factor| self.base_lockout.checked_mul(factor))
+            .unwrap_or(self.max_lockout);
+
+        Some(lockout.min(self.max_lockout))
+    }
+}
+
+struct Failures {
+    count: u32,
+    last_failure: Timestamp,
+    locked_until: Option<Timestamp>,
+}
+
+impl Failures {
+    fn check(&self, now: Timestamp) -> Result<(), Lockout> {
+        match self.locked_until {
+            Some(until) if now < until => Err(Lockout { until }),
+            _ => Ok(()),
+        }
+    }
+
+    fn new(now: Timestamp) -> Self {
+        Self {
+            count: 0,
+            last_failure: now,
+            locked_until: None,
+        }
+    }
+
+    fn is_idle(&self, policy: &ThrottlePolicy, now: Timestamp) -> bool {
+        let quiet_since = self.locked_until.map_or(self.last_failure, |until| {
+            until.max(self.last_failure)
+        });
+
+        quiet_since + policy.idle_timeout <= now
+    }
+
+    /// Failures that went idle are forgotten first, so the backoff keeps
+    /// growing across lockouts without relying on a purge.
+    fn record(&mut self, policy: &ThrottlePolicy, now: Timestamp) {
+        if self.is_idle(policy, now) {
+            *self = Self::new(now);
+        }
+
+        self.count += 1;
+        self.last_failure = now;
+
+        if let Some(lockout) = policy.lockout(self.count) {
+            self.locked_until = Some(now + lockout);
+        }
+    }
+}
+
+fn check<K: Hash + Eq>(
+    failures: &HashMap<K, Failures>,
+    key: &K,
+    now: Timestamp
+) -> Result<(), Lockout> {
+    failures.get(key).map_or(Ok(()), |failures| failures.check(now))
+}
+
+#[derive(Default)]
+pub struct MemoryLoginThrottle {
+    policy: ThrottlePolicy,
+    names: HashMap<String, Failures>,
+    ips: HashMap<IpAddr, Failures>,
+}
+
+impl MemoryLoginThrottle {
+    pub fn new(policy: ThrottlePolicy) -> Self {
+        Self {
+            policy,
+            names: HashMap::new(),
+            ips: HashMap::new(),
+        }
+    }
+
+    /// Drops failures that went idle, which [`LoginThrottle::record_failure`]
+    /// would start counting from scratch anyway.
+    pub fn purge_expired(&mut self, now: Timestamp) {
+        let policy = self.policy;
+
+        self.names.retain(|_, failures| !failures.is_idle(&policy, now));
+        self.ips.retain(|_, failures| !failures.is_idle(&policy, now));
+    }
+}
+
+impl LoginThrottle for MemoryLoginThrottle {
+    fn check(
+        &self,
+        name: &str,
+        ip: IpAddr,
+        now: Timestamp
+    ) -> Result<(), Lockout> {
+        check(&self.names, &name.to_ascii_lowercase(), now)?;
+        check(&self.ips, &ip, now)
+    }
+
+    fn record_failure(&mut self, name: &str, ip: IpAddr, now: Timestamp) {
+        self.names
+            .entry(name.to_ascii_lowercase())
+            .or_insert_with(|| Failures::new(now))
+            .record(&self.policy, now);
+        self.ips
+            .entry(ip)
+            .or_insert_with(|| Failures::new(now))
+            .record(&self.policy, now);
+    }
+
+    fn record_success(&mut self, name: &str) {
+        self.names.remove(&name.to_ascii_lowercase());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    #[test]
+    fn test_lockout_backoff() {
+        let policy = ThrottlePolicy {
+            free_attempts: 2,
+            base_lockout: Duration::from_secs(10),
+            max_lockout: Duration::from_secs(35),
+            idle_timeout: Duration::from_secs(60),
+        };
+
+        assert_eq!(policy.lockout(2), None);
+        assert_eq!(policy.lockout(3), Some(Duration::from_secs(10)));
+        assert_eq!(policy.lockout(4), Some(Duration::from_secs(20)));
+        assert_eq!(policy.lockout(5), Some(Duration::from_secs(35)));
+        assert_eq!(policy.lockout(u32::MAX), Some(Duration::from_secs(35)));
+    }
+
+    #[test]
+    fn test_login_throttle() {
+        let policy = ThrottlePolicy {
+            free_attempts: 1,
+            ..ThrottlePolicy::default()
+        };
+        let lockout = policy.base_lockout;
+        let mut throttle = MemoryLoginThrottle::new(policy);
+        let ip = IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1));
+        let other_ip = IpAddr::V4(Ipv4Addr::new(127, 0, 0, 2));
+        let now = Timestamp::from_unix(1);
+
+        throttle.record_failure("babygronk", ip, now);
+        assert_eq!(throttle.check("babygronk", ip, now), Ok(()));
+
+        throttle.record_failure("BabyGronk", ip, now);
+        let locked = Err(Lockout { until: now + lockout });
+        assert_eq!(throttle.check("babygronk", other_ip, now), locked);
+        assert_eq!(throttle.check("skibidi", ip, now), locked);
+        assert_eq!(throttle.check("skibidi", other_ip, now), Ok(()));
+        assert_eq!(throttle.check("babygronk", ip, now + lockout), Ok(()));
+
+        throttle.record_success("babygronk");
+        assert_eq!(throttle.check("babygronk", other_ip, now), Ok(()));
+        assert_eq!(throttle.check("babygronk", ip, now), locked);
+
+        throttle.purge_expired(now + lockout);
+        assert_eq!(throttle.check("babygronk", ip, now), locked);
+
+        let later = now + lockout;
+        throttle.record_failure("skibidi", ip, later);
+        let locked = Err(Lockout { until: later + lockout * 2 });
+        assert_eq!(throttle.check("skibidi", ip, later), locked);
+
+        let idle = later + lockout * 2 + policy.idle_timeout;
+        throttle.record_failure("skibidi", ip, idle);
+        assert_eq!(throttle.check("skibidi", ip, idle), Ok(()));
+
+        throttle.purge_expired(idle + policy.idle_timeout);
+        assert!(throttle.names.is_empty());
+        assert!(throttle.ips.is_empty());
+    }
+
+    #[test]
+    fn test_idle_failures_purged() {
+        let policy = ThrottlePolicy::default();
+        let idle_timeout = policy.idle_timeout;
+        let mut throttle = MemoryLoginThrottle::new(policy);
+        let now = Timestamp::from_unix(1);
+
+        for i in 0..=255 {
+            let ip = IpAddr::V4(Ipv4Addr::new(10, 0, 0, i));
+            throttle.record_failure(&format!("skibidi{i}"), ip, now);
+        }
+        assert_eq!(throttle.names.len(), 256);
+
+        throttle.purge_expired(now + idle_timeout / 2);
+        assert_eq!(throttle.names.len(), 256);
+
+        throttle.purge_expired(now + idle_timeout);
+        assert!(throttle.names.is_empty());
+        assert!(throttle.ips.is_empty());
+    }
+}
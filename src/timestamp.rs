@@ -0,0 +1,115 @@
+// Wall-clock timestamps for anything that has to survive a restart, and the
+// relative ages the game shows instead of dates
+
+use std::fmt;
+use std::ops::Add;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const MINUTE: u64 = 60;
+const HOUR: u64 = 60 * MINUTE;
+const DAY: u64 = 24 * HOUR;
+const WEEK: u64 = 7 * DAY;
+const MONTH: u64 = 2_628_000;
+const YEAR: u64 = 365 * DAY;
+
+/// Seconds since the unix epoch.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct Timestamp(u64);
+
+impl Timestamp {
+    /// Clocks set before 1970 are treated as the epoch itself.
+    pub fn now() -> Self {
+        let elapsed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+
+        Self(elapsed.as_secs())
+    }
+
+    pub fn from_unix(seconds: u64) -> Self {
+        Self(seconds)
+    }
+
+    pub fn as_unix(&self) -> u64 {
+        self.0
+    }
+
+    /// Seconds between this timestamp and `now`, zero for timestamps in the
+    /// future.
+    pub fn seconds_until(&self, now: Timestamp) -> u64 {
+        now.0.saturating_sub(self.0)
+    }
+
+    /// How long ago this timestamp was as of `now`, formatted the way
+    /// profile, comment and level responses expect it.
+    pub fn age(&self, now: Timestamp) -> Age {
+        Age(self.seconds_until(now))
+    }
+}
+
+/// Sub-second parts of the duration are dropped.
+impl Add<Duration> for Timestamp {
+    type Output = Self;
+
+    fn add(self, duration: Duration) -> Self {
+        Self(self.0.saturating_add(duration.as_secs()))
+    }
+}
+
+/// A relative age such as "3 weeks" or "1 year", only the largest unit is
+/// shown.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct Age(u64);
+
+impl fmt::Display for Age {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let seconds = self.0;
+        let (amount, unit) = match seconds {
+            _ if seconds >= YEAR => (seconds / YEAR, "year"),
+            _ if seconds >= MONTH => (seconds / MONTH, "month"),
+            _ if seconds >= WEEK => (seconds / WEEK, "week"),
+            _ if seconds >= DAY => (seconds / DAY, "day"),
+            _ if seconds >= HOUR => (seconds / HOUR, "hour"),
+            _ if seconds >= MINUTE => (seconds / MINUTE, "minute"),
+            _ => (seconds, "second"),
+        };
+
+        match amount {
+            1 => write!(f, "{amount} {unit}"),
+            _ => write!(f, "{amount} {unit}s"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn age(seconds: u64) -> String {
+        let now = Timestamp::from_unix(1_700_000_000);
+        let then = Timestamp::from_unix(now.as_unix() - seconds);
+        then.age(now).to_string()
+    }
+
+    #[test]
+    fn test_age() {
+        assert_eq!(age(0), "0 seconds");
+        assert_eq!(age(1), "1 second");
+        assert_eq!(age(59), "59 seconds");
+        assert_eq!(age(MINUTE), "1 minute");
+        assert_eq!(age(2 * HOUR + 59 * MINUTE), "2 hours");
+        assert_eq!(age(6 * DAY), "6 days");
+        assert_eq!(age(3 * WEEK + 6 * DAY), "3 weeks");
+        assert_eq!(age(MONTH), "1 month");
+        assert_eq!(age(11 * MONTH), "11 months");
+        assert_eq!(age(YEAR), "1 year");
+        assert_eq!(age(5 * YEAR + 2 * MONTH), "5 years");
+
+        let future = Timestamp::from_unix(1_700_000_060);
+        let now = Timestamp::from_unix(1_700_000_000);
+        assert_eq!(future.age(now).to_string(), "0 seconds");
+        assert!(Timestamp::now() > now);
+    }
+}
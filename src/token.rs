@@ -0,0 +1,11 @@
+// Random tokens for single-use links, shared by password resets and account
+// activation
+
+const TOKEN_LEN: usize = 32;
+
+/// Random hex token, unguessable enough to act as a credential on its own.
+pub(crate) fn random_token() -> Result<String, getrandom::Error> {
+    let mut token = [0; TOKEN_LEN];
+    getrandom::fill(&mut token)?;
+    Ok(token.iter().map(|byte| format!("{byte:02x}")).collect())
+}
@@ -1,24 +1,80 @@
 // All user credential parsing algorithms were figured out by tinkering with
 // their respective fields in the in-game account registration panel
 
-use std::time::Instant;
+use crate::filter::{FilterMode, ProfanityError, WordFilter};
+use crate::timestamp::Timestamp;
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt;
 
 const NAME_LEN_MIN: usize = 3;
 const NAME_LEN_MAX: usize = 14;
 
 #[derive(PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "String"))]
 pub struct Name(String);
 
 #[derive(PartialEq, Debug)]
 pub enum NameError {
     Empty,
     TooShort,
+    Reserved,
+    Profane,
+    InvalidCharacters(Vec<char>),
+    TooLong,
 }
 
+impl fmt::Display for NameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "name has no valid characters"),
+            Self::TooShort => write!(
+                f,
+                "name is shorter than {NAME_LEN_MIN} characters"
+            ),
+            Self::Reserved => write!(f, "name is reserved"),
+            Self::Profane => write!(f, "name contains filtered words"),
+            Self::InvalidCharacters(chars) => write!(
+                f,
+                "name contains invalid characters: {}",
+                describe_chars(chars)
+            ),
+            Self::TooLong => write!(
+                f,
+                "name is longer than {NAME_LEN_MAX} characters"
+            ),
+        }
+    }
+}
+
+impl Error for NameError {}
+
 fn filter_chars(input: &str, predicate: impl FnMut(&char) -> bool) -> String {
     input.chars().filter(predicate).collect()
 }
 
+// Every character `filter_chars` would drop, listed once each
+fn invalid_chars(input: &str, predicate: impl Fn(&char) -> bool) -> Vec<char> {
+    let mut invalid = Vec::new();
+
+    for ch in input.chars().filter(|ch| !predicate(ch)) {
+        if !invalid.contains(&ch) {
+            invalid.push(ch);
+        }
+    }
+
+    invalid
+}
+
+fn describe_chars(chars: &[char]) -> String {
+    chars
+        .iter()
+        .map(|ch| format!("{ch:?}"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
 impl Name {
     pub fn parse(name: &str) -> Result<Self, NameError> {
         let sanitized = filter_chars(name, char::is_ascii_alphanumeric);
@@ -40,34 +96,206 @@ impl Name {
         Ok(Self(sanitized))
     }
 
+    /// Like [`parse`](Self::parse), but reports invalid characters and
+    /// over-length input instead of dropping or truncating them, for forms
+    /// outside of the game.
+    pub fn parse_strict(name: &str) -> Result<Self, NameError> {
+        let invalid = invalid_chars(name, char::is_ascii_alphanumeric);
+
+        if !invalid.is_empty() {
+            return Err(NameError::InvalidCharacters(invalid));
+        }
+
+        if name.len() > NAME_LEN_MAX {
+            return Err(NameError::TooLong);
+        }
+
+        Self::parse(name)
+    }
+
+    /// Like [`parse`](Self::parse), but also rejects names containing words
+    /// caught by `filter`.
+    pub fn parse_filtered(
+        name: &str,
+        filter: &WordFilter
+    ) -> Result<Self, NameError> {
+        let name = Self::parse(name)?;
+
+        if !filter.is_clean(name.as_str()) {
+            return Err(NameError::Profane);
+        }
+
+        Ok(name)
+    }
+
     pub fn as_str(&self) -> &str {
         self.0.as_str()
     }
+
+    /// Case-folded form of the name, names sharing it belong to the same
+    /// account.
+    pub fn canonical(&self) -> String {
+        self.0.to_ascii_lowercase()
+    }
+
+    /// Canonical form with look-alike characters collapsed, e.g. "Adm1n" and
+    /// "AdmIn" both become "admln".
+    pub fn skeleton(&self) -> String {
+        skeleton(&self.canonical())
+    }
+}
+
+impl TryFrom<String> for Name {
+    type Error = NameError;
+
+    fn try_from(name: String) -> Result<Self, Self::Error> {
+        Self::parse(&name)
+    }
+}
+
+fn skeleton(canonical: &str) -> String {
+    canonical
+        .chars()
+        .map(|ch| match ch {
+            '1' | 'i' => 'l',
+            '0' => 'o',
+            _ => ch,
+        })
+        .collect()
+}
+
+const RESERVED_NAMES: &[&str] = &[
+    "robtop",
+    "robtopgames",
+    "admin",
+    "administrator",
+    "moderator",
+    "mod",
+    "staff",
+    "support",
+    "system",
+    "server",
+    "gdps",
+];
+
+/// Server-side rules on top of [`Name::parse`], keeping impersonation
+/// accounts from being registered.
+pub struct NamePolicy {
+    reserved: Vec<String>,
+    detect_confusables: bool,
+    filter: Option<WordFilter>,
+}
+
+impl Default for NamePolicy {
+    fn default() -> Self {
+        Self::new(RESERVED_NAMES, false)
+    }
+}
+
+impl NamePolicy {
+    pub fn new(reserved: &[&str], detect_confusables: bool) -> Self {
+        Self {
+            reserved: reserved
+                .iter()
+                .map(|name| name.to_ascii_lowercase())
+                .collect(),
+            detect_confusables,
+            filter: None,
+        }
+    }
+
+    pub fn detects_confusables(&self) -> bool {
+        self.detect_confusables
+    }
+
+    pub fn set_word_filter(&mut self, filter: WordFilter) -> &mut Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    pub fn check(&self, name: &Name) -> Result<(), NameError> {
+        let reserved = if self.detect_confusables {
+            let name = name.skeleton();
+            self.reserved.iter().any(|reserved| skeleton(reserved) == name)
+        } else {
+            self.reserved.contains(&name.canonical())
+        };
+
+        if reserved {
+            return Err(NameError::Reserved);
+        }
+
+        let profane = self
+            .filter
+            .as_ref()
+            .is_some_and(|filter| !filter.is_clean(name.as_str()));
+
+        if profane {
+            return Err(NameError::Profane);
+        }
+
+        Ok(())
+    }
 }
 
 const PASSWORD_LEN_MIN: usize = 6;
 const PASSWORD_LEN_MAX: usize = 19;
 
+/// Only ever deserialized, passwords must never leave the server in a
+/// response
 #[derive(PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "String"))]
 pub struct Password(String);
 
 #[derive(PartialEq, Debug)]
 pub enum PasswordError {
     Empty,
     TooShort,
+    Common,
+    RepeatedCharacter,
+    MatchesName,
+    InvalidCharacters(Vec<char>),
+    TooLong,
+}
+
+impl fmt::Display for PasswordError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "password has no valid characters"),
+            Self::TooShort => write!(
+                f,
+                "password is shorter than {PASSWORD_LEN_MIN} characters"
+            ),
+            Self::Common => write!(f, "password is too common"),
+            Self::RepeatedCharacter => {
+                write!(f, "password is a single repeated character")
+            }
+            Self::MatchesName => write!(f, "password is the same as the name"),
+            Self::InvalidCharacters(chars) => write!(
+                f,
+                "password contains invalid characters: {}",
+                describe_chars(chars)
+            ),
+            Self::TooLong => write!(
+                f,
+                "password is longer than {PASSWORD_LEN_MAX} characters"
+            ),
+        }
+    }
 }
 
-const PASSWORD_ALLOWED_SPECIAL_CHARS: &'static str = "-_";
+impl Error for PasswordError {}
+
+const PASSWORD_ALLOWED_SPECIAL_CHARS: &str = "-_";
+
+fn is_password_char(ch: &char) -> bool {
+    ch.is_ascii_alphanumeric() || PASSWORD_ALLOWED_SPECIAL_CHARS.contains(*ch)
+}
 
 impl Password {
     pub fn parse(password: &str) -> Result<Self, PasswordError> {
-        let sanitized = filter_chars(
-            password,
-            |ch| {
-                ch.is_ascii_alphanumeric() ||
-                PASSWORD_ALLOWED_SPECIAL_CHARS.contains(*ch)
-            }
-        );
+        let sanitized = filter_chars(password, is_password_char);
 
         if sanitized.is_empty() {
             return Err(PasswordError::Empty);
@@ -86,15 +314,95 @@ impl Password {
         Ok(Self(sanitized))
     }
 
+    /// Like [`parse`](Self::parse), but reports invalid characters and
+    /// over-length input instead of dropping or truncating them.
+    pub fn parse_strict(password: &str) -> Result<Self, PasswordError> {
+        let invalid = invalid_chars(password, is_password_char);
+
+        if !invalid.is_empty() {
+            return Err(PasswordError::InvalidCharacters(invalid));
+        }
+
+        if password.len() > PASSWORD_LEN_MAX {
+            return Err(PasswordError::TooLong);
+        }
+
+        Self::parse(password)
+    }
+
     pub fn as_str(&self) -> &str {
         self.0.as_str()
     }
 }
 
+impl TryFrom<String> for Password {
+    type Error = PasswordError;
+
+    fn try_from(password: String) -> Result<Self, Self::Error> {
+        Self::parse(&password)
+    }
+}
+
+const COMMON_PASSWORDS: &str = include_str!("common_passwords.txt");
+
+/// Opt-in server-side rules on top of [`Password::parse`], which only
+/// enforces what the client does.
+pub struct PasswordPolicy {
+    common: HashSet<String>,
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        Self::new(COMMON_PASSWORDS)
+    }
+}
+
+impl PasswordPolicy {
+    /// Takes a list of common passwords, one per line, where empty lines and
+    /// lines starting with `#` are ignored.
+    pub fn new(common: &str) -> Self {
+        Self {
+            common: common
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(str::to_ascii_lowercase)
+                .collect(),
+        }
+    }
+
+    pub fn check(
+        &self,
+        password: &Password,
+        name: &Name
+    ) -> Result<(), PasswordError> {
+        let lowercase = password.as_str().to_ascii_lowercase();
+
+        if self.common.contains(&lowercase) {
+            return Err(PasswordError::Common);
+        }
+
+        let mut chars = lowercase.chars();
+        let first = chars.next();
+
+        if chars.all(|ch| Some(ch) == first) {
+            return Err(PasswordError::RepeatedCharacter);
+        }
+
+        if lowercase == name.canonical() {
+            return Err(PasswordError::MatchesName);
+        }
+
+        Ok(())
+    }
+}
+
 const EMAIL_LEN_MIN: usize = 4;
 const EMAIL_LEN_MAX: usize = 49;
 
 #[derive(PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "String"))]
 pub struct Email(String);
 
 #[derive(PartialEq, Debug)]
@@ -102,19 +410,43 @@ pub enum EmailError {
     Empty,
     TooShort,
     Malformed,
+    InvalidCharacters(Vec<char>),
+    TooLong,
 }
 
-const EMAIL_ALLOWED_SPECIAL_CHARS: &'static str = "-_@.";
+impl fmt::Display for EmailError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "email has no valid characters"),
+            Self::TooShort => write!(
+                f,
+                "email is shorter than {EMAIL_LEN_MIN} characters"
+            ),
+            Self::Malformed => write!(f, "email is malformed"),
+            Self::InvalidCharacters(chars) => write!(
+                f,
+                "email contains invalid characters: {}",
+                describe_chars(chars)
+            ),
+            Self::TooLong => write!(
+                f,
+                "email is longer than {EMAIL_LEN_MAX} characters"
+            ),
+        }
+    }
+}
+
+impl Error for EmailError {}
+
+const EMAIL_ALLOWED_SPECIAL_CHARS: &str = "-_@.";
+
+fn is_email_char(ch: &char) -> bool {
+    ch.is_ascii_alphanumeric() || EMAIL_ALLOWED_SPECIAL_CHARS.contains(*ch)
+}
 
 impl Email {
     pub fn parse(email: &str) -> Result<Self, EmailError> {
-        let sanitized = filter_chars(
-            email,
-            |ch| {
-                ch.is_ascii_alphanumeric()
-                || EMAIL_ALLOWED_SPECIAL_CHARS.contains(*ch)
-            }
-        );
+        let sanitized = filter_chars(email, is_email_char);
 
         if sanitized.is_empty() {
             return Err(EmailError::Empty);
@@ -166,6 +498,22 @@ impl Email {
         Ok(Self(sanitized))
     }
 
+    /// Like [`parse`](Self::parse), but reports invalid characters and
+    /// over-length input instead of dropping or truncating them.
+    pub fn parse_strict(email: &str) -> Result<Self, EmailError> {
+        let invalid = invalid_chars(email, is_email_char);
+
+        if !invalid.is_empty() {
+            return Err(EmailError::InvalidCharacters(invalid));
+        }
+
+        if email.len() > EMAIL_LEN_MAX {
+            return Err(EmailError::TooLong);
+        }
+
+        Self::parse(email)
+    }
+
     fn find_last(input: &str, ch: char) -> Option<usize> {
         input
         .chars()
@@ -180,6 +528,16 @@ impl Email {
     }
 }
 
+impl TryFrom<String> for Email {
+    type Error = EmailError;
+
+    fn try_from(email: String) -> Result<Self, Self::Error> {
+        Self::parse(&email)
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(from = "RawSocialMediaHandles"))]
 pub struct SocialMediaHandles {
     youtube: Option<String>,
     // Renamed to X but the game still displays it as Twitter,
@@ -188,7 +546,59 @@ pub struct SocialMediaHandles {
     twitch: Option<String>,
 }
 
-const HANDLE_ALLOWED_SPECIAL_CHARS: &'static str = "-_,' ";
+/// Handles as they come in, sanitized by [`SocialMediaHandles::new`] before
+/// they are used
+#[cfg(feature = "serde")]
+#[derive(Default, serde::Deserialize)]
+#[serde(default)]
+struct RawSocialMediaHandles {
+    youtube: Option<String>,
+    twitter: Option<String>,
+    twitch: Option<String>,
+}
+
+#[cfg(feature = "serde")]
+impl From<RawSocialMediaHandles> for SocialMediaHandles {
+    fn from(raw: RawSocialMediaHandles) -> Self {
+        Self::new(
+            raw.youtube.as_deref().unwrap_or_default(),
+            raw.twitter.as_deref().unwrap_or_default(),
+            raw.twitch.as_deref().unwrap_or_default(),
+        )
+    }
+}
+
+const HANDLE_ALLOWED_SPECIAL_CHARS: &str = "-_,' ";
+
+fn is_handle_char(ch: &char) -> bool {
+    ch.is_ascii_alphanumeric() || HANDLE_ALLOWED_SPECIAL_CHARS.contains(*ch)
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum SocialMedia {
+    Youtube,
+    Twitter,
+    Twitch,
+}
+
+#[derive(PartialEq, Debug)]
+pub enum HandleError {
+    InvalidCharacters(SocialMedia, Vec<char>),
+}
+
+impl fmt::Display for HandleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::InvalidCharacters(social_media, chars) => write!(
+                f,
+                "{social_media:?} handle contains invalid characters: {}",
+                describe_chars(chars)
+            ),
+        }
+    }
+}
+
+impl Error for HandleError {}
 
 impl SocialMediaHandles {
     pub fn new(youtube: &str, twitter: &str, twitch: &str) -> Self {
@@ -199,6 +609,30 @@ impl SocialMediaHandles {
         }
     }
 
+    /// Like [`new`](Self::new), but reports invalid characters instead of
+    /// dropping them.
+    pub fn new_strict(
+        youtube: &str,
+        twitter: &str,
+        twitch: &str
+    ) -> Result<Self, HandleError> {
+        let handles = [
+            (SocialMedia::Youtube, youtube),
+            (SocialMedia::Twitter, twitter),
+            (SocialMedia::Twitch, twitch),
+        ];
+
+        for (media, handle) in handles {
+            let invalid = invalid_chars(handle, is_handle_char);
+
+            if !invalid.is_empty() {
+                return Err(HandleError::InvalidCharacters(media, invalid));
+            }
+        }
+
+        Ok(Self::new(youtube, twitter, twitch))
+    }
+
     pub fn youtube(&self) -> Option<String> {
         self.youtube.clone()
     }
@@ -226,14 +660,32 @@ impl SocialMediaHandles {
         self
     }
 
+    /// Runs every handle through `filter`. Rejecting leaves the handles
+    /// untouched if any of them is caught.
+    pub fn apply_filter(
+        &mut self,
+        filter: &WordFilter,
+        mode: FilterMode
+    ) -> Result<&mut Self, ProfanityError> {
+        let apply = |handle: &Option<String>| {
+            handle
+                .as_deref()
+                .map(|handle| filter.apply(handle, mode))
+                .transpose()
+        };
+
+        let youtube = apply(&self.youtube)?;
+        let twitter = apply(&self.twitter)?;
+        let twitch = apply(&self.twitch)?;
+
+        self.youtube = youtube;
+        self.twitter = twitter;
+        self.twitch = twitch;
+        Ok(self)
+    }
+
     fn sanitize_social_media_handle(handle: &str) -> Option<String> {
-        let sanitized = filter_chars(
-            handle,
-            |ch| {
-                ch.is_ascii_alphanumeric()
-                || HANDLE_ALLOWED_SPECIAL_CHARS.contains(*ch)
-            }
-        );
+        let sanitized = filter_chars(handle, is_handle_char);
 
         match sanitized.as_str() {
             "" => None,
@@ -242,7 +694,13 @@ impl SocialMediaHandles {
     }
 }
 
-#[derive(Default)]
+/// Everything that can be unlocked and equipped in the icon kit, IDs are
+/// 1-based while colors index into the 0-based color palette.
+#[derive(PartialEq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+// Derives inherent `IconSet::serialize` and `IconSet::deserialize`, wrapped
+// by the trait impls below so every deserialized set is validated
+#[cfg_attr(feature = "serde", serde(remote = "Self", default))]
 pub struct IconSet {
     pub icon_id: u32,
     pub ship_id: u32,
@@ -253,11 +711,184 @@ pub struct IconSet {
     pub robot_id: u32,
     pub spider_id: u32,
     pub swing_id: u32,
-    pub glow_id: u32,
     pub death_effect_id: u32,
+    pub streak_id: u32,
+    pub ship_fire_id: u32,
+    pub primary_color: u32,
+    pub secondary_color: u32,
+    pub glow_color: u32,
+    pub glow: bool,
+    /// Sent as `special`, stored and echoed back as is
+    pub special: u32,
 }
 
-#[derive(Default)]
+impl Default for IconSet {
+    /// The icons and colors of a freshly installed game.
+    fn default() -> Self {
+        Self {
+            icon_id: 1,
+            ship_id: 1,
+            jetpack_id: 1,
+            ball_id: 1,
+            ufo_id: 1,
+            wave_id: 1,
+            robot_id: 1,
+            spider_id: 1,
+            swing_id: 1,
+            death_effect_id: 1,
+            streak_id: 1,
+            ship_fire_id: 1,
+            primary_color: 0,
+            secondary_color: 3,
+            glow_color: 3,
+            glow: false,
+            special: 0,
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Cosmetic {
+    Icon,
+    Ship,
+    Jetpack,
+    Ball,
+    Ufo,
+    Wave,
+    Robot,
+    Spider,
+    Swing,
+    DeathEffect,
+    Streak,
+    ShipFire,
+    Color,
+}
+
+impl Cosmetic {
+    /// Highest ID available as of 2.207.
+    pub fn max_id(&self) -> u32 {
+        match self {
+            Self::Icon => 485,
+            Self::Ship => 169,
+            Self::Jetpack => 8,
+            Self::Ball => 118,
+            Self::Ufo => 149,
+            Self::Wave => 96,
+            Self::Robot => 68,
+            Self::Spider => 69,
+            Self::Swing => 43,
+            Self::DeathEffect => 20,
+            Self::Streak => 7,
+            Self::ShipFire => 6,
+            Self::Color => 106,
+        }
+    }
+
+    pub fn min_id(&self) -> u32 {
+        match self {
+            Self::Color => 0,
+            _ => 1,
+        }
+    }
+}
+
+#[derive(PartialEq, Debug)]
+pub enum IconError {
+    OutOfRange(Cosmetic, u32),
+}
+
+impl fmt::Display for IconError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::OutOfRange(cosmetic, id) => {
+                write!(f, "{cosmetic:?} ID {id} is out of range")
+            }
+        }
+    }
+}
+
+impl Error for IconError {}
+
+impl IconSet {
+    fn cosmetics(&self) -> [(Cosmetic, u32); 15] {
+        [
+            (Cosmetic::Icon, self.icon_id),
+            (Cosmetic::Ship, self.ship_id),
+            (Cosmetic::Jetpack, self.jetpack_id),
+            (Cosmetic::Ball, self.ball_id),
+            (Cosmetic::Ufo, self.ufo_id),
+            (Cosmetic::Wave, self.wave_id),
+            (Cosmetic::Robot, self.robot_id),
+            (Cosmetic::Spider, self.spider_id),
+            (Cosmetic::Swing, self.swing_id),
+            (Cosmetic::DeathEffect, self.death_effect_id),
+            (Cosmetic::Streak, self.streak_id),
+            (Cosmetic::ShipFire, self.ship_fire_id),
+            (Cosmetic::Color, self.primary_color),
+            (Cosmetic::Color, self.secondary_color),
+            (Cosmetic::Color, self.glow_color),
+        ]
+    }
+
+    /// Rejects IDs the game doesn't have, modded clients happily send
+    /// whatever they like.
+    pub fn validate(&self) -> Result<(), IconError> {
+        for (cosmetic, id) in self.cosmetics() {
+            if !(cosmetic.min_id()..=cosmetic.max_id()).contains(&id) {
+                return Err(IconError::OutOfRange(cosmetic, id));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The fields as named in profile and leaderboard responses.
+    pub fn to_fields(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("color1", self.primary_color.to_string()),
+            ("color2", self.secondary_color.to_string()),
+            ("color3", self.glow_color.to_string()),
+            ("accIcon", self.icon_id.to_string()),
+            ("accShip", self.ship_id.to_string()),
+            ("accBall", self.ball_id.to_string()),
+            ("accBird", self.ufo_id.to_string()),
+            ("accDart", self.wave_id.to_string()),
+            ("accRobot", self.robot_id.to_string()),
+            ("accGlow", u8::from(self.glow).to_string()),
+            ("accSpider", self.spider_id.to_string()),
+            ("accExplosion", self.death_effect_id.to_string()),
+            ("accSwing", self.swing_id.to_string()),
+            ("accJetpack", self.jetpack_id.to_string()),
+            ("accStreak", self.streak_id.to_string()),
+            ("accShipFire", self.ship_fire_id.to_string()),
+            ("special", self.special.to_string()),
+        ]
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for IconSet {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S
+    ) -> Result<S::Ok, S::Error> {
+        Self::serialize(self, serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for IconSet {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D
+    ) -> Result<Self, D::Error> {
+        let icons = Self::deserialize(deserializer)?;
+        icons.validate().map_err(serde::de::Error::custom)?;
+        Ok(icons)
+    }
+}
+
+#[derive(Default, PartialEq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Stats {
     pub stars: u32,
     pub moons: u32,
@@ -269,7 +900,52 @@ pub struct Stats {
     pub orbs: u32,
 }
 
-#[derive(Default)]
+#[derive(PartialEq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Stat {
+    Stars,
+    Moons,
+    Coins,
+    UserCoins,
+    Diamonds,
+    Demons,
+    CreatorPoints,
+    Orbs,
+}
+
+impl Stats {
+    pub fn get(&self, stat: Stat) -> u32 {
+        match stat {
+            Stat::Stars => self.stars,
+            Stat::Moons => self.moons,
+            Stat::Coins => self.coins,
+            Stat::UserCoins => self.user_coins,
+            Stat::Diamonds => self.diamonds,
+            Stat::Demons => self.demons,
+            Stat::CreatorPoints => self.creator_points,
+            Stat::Orbs => self.orbs,
+        }
+    }
+
+    pub fn set(&mut self, stat: Stat, value: u32) -> &mut Self {
+        let field = match stat {
+            Stat::Stars => &mut self.stars,
+            Stat::Moons => &mut self.moons,
+            Stat::Coins => &mut self.coins,
+            Stat::UserCoins => &mut self.user_coins,
+            Stat::Diamonds => &mut self.diamonds,
+            Stat::Demons => &mut self.demons,
+            Stat::CreatorPoints => &mut self.creator_points,
+            Stat::Orbs => &mut self.orbs,
+        };
+
+        *field = value;
+        self
+    }
+}
+
+#[derive(Default, PartialEq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 pub enum Ban {
     #[default]
@@ -279,16 +955,163 @@ pub enum Ban {
     LeaderboardAndCreatorBan,
 }
 
-pub enum AllowMessagesFrom {}
-pub enum AllowFriendRequestsFrom {}
-pub enum DisplayCommentHistoryTo {}
+impl Ban {
+    // Bit 0 is the leaderboard ban and bit 1 the creator ban
+    fn from_bits(bits: u8) -> Self {
+        match bits & 0b11 {
+            0 => Self::None,
+            1 => Self::LeaderboardBan,
+            2 => Self::CreatorBan,
+            _ => Self::LeaderboardAndCreatorBan,
+        }
+    }
+
+    /// Both bans together, e.g. a creator ban combined with a leaderboard
+    /// ban becomes [`Ban::LeaderboardAndCreatorBan`].
+    pub fn combine(self, other: Ban) -> Self {
+        Self::from_bits(self as u8 | other as u8)
+    }
+
+    /// This ban with `other` lifted, keeping whatever else was in place.
+    pub fn lift(self, other: Ban) -> Self {
+        Self::from_bits(self as u8 & !(other as u8))
+    }
+
+    pub fn is_leaderboard_banned(&self) -> bool {
+        *self as u8 & Self::LeaderboardBan as u8 != 0
+    }
+
+    pub fn is_creator_banned(&self) -> bool {
+        *self as u8 & Self::CreatorBan as u8 != 0
+    }
+}
+
+#[derive(Default, PartialEq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Activation {
+    /// Waiting for the email to be verified, the client reports these
+    /// accounts as not activated when logging in
+    #[default]
+    Pending,
+    Active,
+}
+
+impl Activation {
+    pub fn is_active(&self) -> bool {
+        *self == Self::Active
+    }
+}
+
+#[derive(Default, PartialEq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AllowMessagesFrom {
+    #[default]
+    All,
+    Friends,
+    None,
+}
+
+impl AllowMessagesFrom {
+    /// Parses the `mS` value sent by the client.
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "0" => Some(Self::All),
+            "1" => Some(Self::Friends),
+            "2" => Some(Self::None),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::All => "0",
+            Self::Friends => "1",
+            Self::None => "2",
+        }
+    }
+}
+
+#[derive(Default, PartialEq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AllowFriendRequestsFrom {
+    #[default]
+    All,
+    None,
+}
 
+impl AllowFriendRequestsFrom {
+    /// Parses the `frS` value sent by the client.
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "0" => Some(Self::All),
+            "1" => Some(Self::None),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::All => "0",
+            Self::None => "1",
+        }
+    }
+}
+
+#[derive(Default, PartialEq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DisplayCommentHistoryTo {
+    #[default]
+    All,
+    Friends,
+    /// Only the account owner
+    None,
+}
+
+impl DisplayCommentHistoryTo {
+    /// Parses the `cS` value sent by the client.
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "0" => Some(Self::All),
+            "1" => Some(Self::Friends),
+            "2" => Some(Self::None),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::All => "0",
+            Self::Friends => "1",
+            Self::None => "2",
+        }
+    }
+}
+
+#[derive(Default, PartialEq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PrivacySettings {
+    pub messages: AllowMessagesFrom,
+    pub friend_requests: AllowFriendRequestsFrom,
+    pub comment_history: DisplayCommentHistoryTo,
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct User {
     pub id: u64,
     pub name: Name,
     pub email: Email,
     pub social_media_handles: SocialMediaHandles,
-    pub created_at: Instant,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub privacy: PrivacySettings,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub icons: IconSet,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub stats: Stats,
+    pub created_at: Timestamp,
+    pub ban: Ban,
+    pub activation: Activation,
+    /// Locked out by a moderator, unlike a [`Ban`] this prevents logging in
+    pub disabled: bool,
 }
 
 impl User {
@@ -297,18 +1120,36 @@ impl User {
         name: Name,
         email: Email,
         social_media_handles: SocialMediaHandles,
-        created_at: Instant
+        created_at: Timestamp
     ) -> Self {
         Self {
             id,
             name,
             email,
             social_media_handles,
+            privacy: PrivacySettings::default(),
+            icons: IconSet::default(),
+            stats: Stats::default(),
             created_at,
+            ban: Ban::None,
+            activation: Activation::Pending,
+            disabled: false,
         }
     }
 }
 
+/// A valid user for tests that don't care about the details.
+#[cfg(test)]
+pub(crate) fn test_user(id: u64) -> User {
+    User::new(
+        id,
+        Name::parse("babygronk").unwrap(),
+        Email::parse("babygronk@rizz.com").unwrap(),
+        SocialMediaHandles::new("", "", ""),
+        Timestamp::from_unix(0),
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -321,6 +1162,42 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_name_policy() -> Result<(), NameError> {
+        let name = Name::parse("AdmIn0")?;
+        assert_eq!(name.canonical(), "admin0");
+        assert_eq!(name.skeleton(), "admlno");
+
+        let policy = NamePolicy::default();
+        let check = |name| policy.check(&Name::parse(name)?);
+        assert_eq!(check("RobTop"), Err(NameError::Reserved));
+        assert_eq!(check("R0bT0p"), Ok(()));
+        assert_eq!(check("RobTopFan"), Ok(()));
+
+        let mut policy = NamePolicy::new(RESERVED_NAMES, true);
+        policy.set_word_filter(WordFilter::parse("nuts"));
+        let check = |name| policy.check(&Name::parse(name)?);
+        assert_eq!(check("R0bT0p"), Err(NameError::Reserved));
+        assert_eq!(check("Adm1n"), Err(NameError::Reserved));
+        assert_eq!(check("DeezNut5"), Err(NameError::Profane));
+        Ok(())
+    }
+
+    #[test]
+    fn test_filtered_name_parsing() -> Result<(), NameError> {
+        let filter = WordFilter::parse("nuts\n!chestnuts");
+        assert_eq!(
+            Name::parse_filtered("deez nuts", &filter),
+            Err(NameError::Profane)
+        );
+        assert_eq!(Name::parse_filtered("?!", &filter), Err(NameError::Empty));
+        assert_eq!(
+            Name::parse_filtered("chestnuts", &filter)?.as_str(),
+            "chestnuts"
+        );
+        Ok(())
+    }
+
     #[test]
     fn test_password_parsing() -> Result<(), PasswordError> {
         assert_eq!(Password::parse("/##><&#"), Err(PasswordError::Empty));
@@ -329,6 +1206,20 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_password_policy() -> Result<(), PasswordError> {
+        let policy = PasswordPolicy::default();
+        let name = Name::parse("babygronk").unwrap();
+        let check = |password| policy.check(&Password::parse(password)?, &name);
+
+        assert_eq!(check("Password1"), Err(PasswordError::Common));
+        assert_eq!(check("GeometryDash"), Err(PasswordError::Common));
+        assert_eq!(check("aAaAaAa"), Err(PasswordError::RepeatedCharacter));
+        assert_eq!(check("BabyGronk"), Err(PasswordError::MatchesName));
+        assert_eq!(check("_deez-nuts"), Ok(()));
+        Ok(())
+    }
+
     #[test]
     fn test_email_parsing() -> Result<(), EmailError> {
         assert_eq!(Email::parse("+%÷>)"), Err(EmailError::Empty));
@@ -339,6 +1230,47 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_strict_parsing() {
+        assert_eq!(
+            Name::parse_strict("baby gronk!!"),
+            Err(NameError::InvalidCharacters(vec![' ', '!']))
+        );
+        assert_eq!(
+            Name::parse_strict("babygronkrizzler"),
+            Err(NameError::TooLong)
+        );
+        assert_eq!(Name::parse_strict("ab"), Err(NameError::TooShort));
+        assert!(Name::parse_strict("babygronk").is_ok());
+
+        assert_eq!(
+            Password::parse_strict("deez nuts"),
+            Err(PasswordError::InvalidCharacters(vec![' ']))
+        );
+        assert_eq!(
+            Password::parse_strict("_deez-nuts_deez-nuts"),
+            Err(PasswordError::TooLong)
+        );
+        assert!(Password::parse_strict("_deez-nuts").is_ok());
+
+        assert_eq!(
+            Email::parse_strict("a+b@c.de"),
+            Err(EmailError::InvalidCharacters(vec!['+']))
+        );
+        assert_eq!(Email::parse_strict("a@b"), Err(EmailError::Malformed));
+        assert_eq!(
+            Email::parse_strict(&format!("{}@b.cd", "a".repeat(45))),
+            Err(EmailError::TooLong)
+        );
+
+        let invalid = vec!['~'];
+        assert_eq!(
+            SocialMediaHandles::new_strict("xd", "~xd", "").err(),
+            Some(HandleError::InvalidCharacters(SocialMedia::Twitter, invalid))
+        );
+        assert!(SocialMediaHandles::new_strict("xd", "-_,' ", "").is_ok());
+    }
+
     #[test]
     fn test_social_media_handles() {
         let mut handles = SocialMediaHandles::new("```", "-_,' ", "~xd");
@@ -351,4 +1283,104 @@ mod tests {
         assert_eq!(handles.twitter(), None);
         assert_eq!(handles.twitch(), Some("-_,' ".to_string()));
     }
+
+    #[test]
+    fn test_social_media_handle_filtering() {
+        let filter = WordFilter::parse("nuts");
+        let mut handles = SocialMediaHandles::new("deez nuts", "", "xd");
+
+        assert!(handles.apply_filter(&filter, FilterMode::Reject).is_err());
+        assert_eq!(handles.youtube(), Some("deez nuts".to_string()));
+
+        assert!(handles.apply_filter(&filter, FilterMode::Censor).is_ok());
+        assert_eq!(handles.youtube(), Some("deez ****".to_string()));
+        assert_eq!(handles.twitter(), None);
+        assert_eq!(handles.twitch(), Some("xd".to_string()));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() -> Result<(), serde_json::Error> {
+        let name: Name = serde_json::from_str(r#""deez~nuts""#)?;
+        assert_eq!(name.as_str(), "deeznuts");
+        assert!(serde_json::from_str::<Name>(r#""xd""#).is_err());
+        assert!(serde_json::from_str::<Email>(r#""deez@nuts""#).is_err());
+        assert!(serde_json::from_str::<Password>(r#""deeznuts""#).is_ok());
+
+        let handles: SocialMediaHandles =
+            serde_json::from_str(r#"{"youtube":"~xd","twitch":""}"#)?;
+        assert_eq!(handles.youtube(), Some("xd".to_string()));
+        assert_eq!(handles.twitter(), None);
+        assert_eq!(handles.twitch(), None);
+
+        let user = User::new(
+            1,
+            name,
+            Email::parse("deez@nuts.com").unwrap(),
+            handles,
+            Timestamp::from_unix(1_700_000_000),
+        );
+        let json = serde_json::to_string(&user)?;
+        assert!(json.contains(r#""created_at":1700000000"#));
+
+        let user: User = serde_json::from_str(&json)?;
+        assert_eq!(user.name.as_str(), "deeznuts");
+        assert_eq!(user.email.as_str(), "deez@nuts.com");
+        assert_eq!(user.created_at, Timestamp::from_unix(1_700_000_000));
+        assert_eq!(user.icons, IconSet::default());
+
+        let icons: IconSet = serde_json::from_str(r#"{"ship_id":169}"#)?;
+        assert_eq!(icons.ship_id, 169);
+        assert_eq!(icons.secondary_color, 3);
+        assert!(serde_json::from_str::<IconSet>(r#"{"ship_id":170}"#).is_err());
+        assert_eq!(user.ban, Ban::None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_icon_set() {
+        let mut icons = IconSet::default();
+        assert!(icons.validate().is_ok());
+
+        icons.ship_id = 170;
+        assert_eq!(
+            icons.validate(),
+            Err(IconError::OutOfRange(Cosmetic::Ship, 170))
+        );
+
+        icons.ship_id = 169;
+        icons.glow_color = 107;
+        assert_eq!(
+            icons.validate(),
+            Err(IconError::OutOfRange(Cosmetic::Color, 107))
+        );
+
+        icons.glow_color = 0;
+        icons.glow = true;
+        assert!(icons.validate().is_ok());
+
+        let fields = icons.to_fields();
+        assert!(fields.contains(&("accShip", "169".to_string())));
+        assert!(fields.contains(&("accGlow", "1".to_string())));
+        assert!(fields.contains(&("color2", "3".to_string())));
+        assert!(fields.contains(&("color3", "0".to_string())));
+
+        icons.icon_id = 0;
+        assert_eq!(
+            icons.validate(),
+            Err(IconError::OutOfRange(Cosmetic::Icon, 0))
+        );
+    }
+
+    #[test]
+    fn test_ban_combining() {
+        let both = Ban::LeaderboardAndCreatorBan;
+        assert_eq!(Ban::None.combine(Ban::CreatorBan), Ban::CreatorBan);
+        assert_eq!(Ban::CreatorBan.combine(Ban::LeaderboardBan), both);
+        assert_eq!(both.combine(Ban::LeaderboardBan), both);
+        assert_eq!(both.lift(Ban::LeaderboardBan), Ban::CreatorBan);
+        assert_eq!(Ban::CreatorBan.lift(Ban::LeaderboardBan), Ban::CreatorBan);
+        assert!(both.is_leaderboard_banned() && both.is_creator_banned());
+        assert!(!Ban::CreatorBan.is_leaderboard_banned());
+    }
 }
@@ -0,0 +1,104 @@
+// Cyclic XOR followed by URL-safe base64, which the game uses to obfuscate
+// most of the values it doesn't want players to edit by hand
+
+use base64::alphabet::URL_SAFE;
+use base64::engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig};
+use base64::Engine;
+use std::error::Error;
+use std::fmt;
+
+// The client pads its output but some mods strip the padding, accept both
+const ENGINE: GeneralPurpose = GeneralPurpose::new(
+    &URL_SAFE,
+    GeneralPurposeConfig::new()
+        .with_decode_padding_mode(DecodePaddingMode::Indifferent)
+);
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum XorKey {
+    Message,
+    VaultCode,
+    Challenges,
+    LevelPassword,
+    Comment,
+    AccountPassword,
+    LevelLeaderboard,
+    Level,
+    LikeOrRate,
+    Rewards,
+    Stats,
+}
+
+impl XorKey {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Message => "14251",
+            Self::VaultCode => "19283",
+            Self::Challenges => "19847",
+            Self::LevelPassword => "26364",
+            Self::Comment => "29481",
+            Self::AccountPassword => "37526",
+            Self::LevelLeaderboard => "39673",
+            Self::Level => "41274",
+            Self::LikeOrRate => "58281",
+            Self::Rewards => "59182",
+            Self::Stats => "85271",
+        }
+    }
+}
+
+#[derive(PartialEq, Debug)]
+pub enum XorError {
+    InvalidBase64,
+    InvalidUtf8,
+}
+
+impl fmt::Display for XorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::InvalidBase64 => write!(f, "input is not valid base64"),
+            Self::InvalidUtf8 => write!(f, "decoded input is not valid UTF-8"),
+        }
+    }
+}
+
+impl Error for XorError {}
+
+pub fn cipher(input: &[u8], key: XorKey) -> Vec<u8> {
+    input
+        .iter()
+        .zip(key.as_str().as_bytes().iter().cycle())
+        .map(|(byte, key)| byte ^ key)
+        .collect()
+}
+
+pub fn encode(input: &str, key: XorKey) -> String {
+    ENGINE.encode(cipher(input.as_bytes(), key))
+}
+
+pub fn decode(input: &str, key: XorKey) -> Result<String, XorError> {
+    let decoded = ENGINE
+        .decode(input)
+        .map_err(|_| XorError::InvalidBase64)?;
+
+    String::from_utf8(cipher(&decoded, key))
+        .map_err(|_| XorError::InvalidUtf8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_xor_cipher() -> Result<(), XorError> {
+        let input = "babygronk";
+        let encrypted = cipher(input.as_bytes(), XorKey::Message);
+
+        assert_eq!(cipher(&encrypted, XorKey::Message), input.as_bytes());
+        assert_eq!(encode(input, XorKey::LevelPassword), "UFdRT1NAWV1d");
+        assert_eq!(decode("UFdRT1NAWV1d", XorKey::LevelPassword)?, input);
+        assert_eq!(decode("!!!", XorKey::Stats), Err(XorError::InvalidBase64));
+        assert_eq!(decode("_w==", XorKey::Stats), Err(XorError::InvalidUtf8));
+        Ok(())
+    }
+}